@@ -0,0 +1,278 @@
+// Interest calculations for loan repayments. Interest rates are stored in basis points (1% = 100 bps) per year and amounts are in
+// DassiCoin base units. Every calculation returns None on overflow so processor can fail with AmountOverflow instead of panicking.
+
+use std::convert::TryInto;
+
+pub const BASIS_POINTS: u128 = 10_000;
+pub const DAYS_IN_ONE_YEAR: u128 = 365;
+pub const MONTHLY_EMI_INTERVAL_DAYS: u16 = 30;
+pub const WEEKLY_EMI_INTERVAL_DAYS: u16 = 7;
+pub const MAX_CUSTOM_EMI_INTERVAL_DAYS: u16 = 365;
+pub const NUM_SECONDS_IN_ONE_DAY: u64 = 86400;
+// for prototype annual interest rate can't be more than 100%
+pub const MAX_ANNUAL_INTEREST_RATE_BPS: u16 = 10_000;
+// period interest rates are kept as fixed point numbers with 12 decimals
+const RATE_SCALE: u128 = 1_000_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmortizationType {
+    // interest is charged on full loan amount for whole tenure and split equally between emis
+    Flat = 0,
+    // interest of every emi is charged only on principal which is not repaid yet
+    ReducingBalance = 1,
+}
+
+impl AmortizationType {
+    pub fn from_u8(input: u8) -> Option<Self> {
+        match input {
+            0 => Some(AmortizationType::Flat),
+            1 => Some(AmortizationType::ReducingBalance),
+            _ => None,
+        }
+    }
+}
+
+pub enum RepaymentIntervalType {
+    Monthly = 0,
+    Weekly = 1,
+    // guarantor chooses number of days between emis
+    Custom = 2,
+}
+
+impl RepaymentIntervalType {
+    pub fn from_u8(input: u8) -> Option<Self> {
+        match input {
+            0 => Some(RepaymentIntervalType::Monthly),
+            1 => Some(RepaymentIntervalType::Weekly),
+            2 => Some(RepaymentIntervalType::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn get_emi_interval_days(&self, custom_interval_days: u16) -> Option<u16> {
+        match self {
+            RepaymentIntervalType::Monthly => Some(MONTHLY_EMI_INTERVAL_DAYS),
+            RepaymentIntervalType::Weekly => Some(WEEKLY_EMI_INTERVAL_DAYS),
+            RepaymentIntervalType::Custom => {
+                if custom_interval_days == 0 || custom_interval_days > MAX_CUSTOM_EMI_INTERVAL_DAYS {
+                    None
+                } else {
+                    Some(custom_interval_days)
+                }
+            }
+        }
+    }
+}
+
+// last date to pay emi number emi_index (starting from 0) without being late
+pub fn calculate_emi_due_timestamp(
+    first_repayment_last_date_timestamp: u64,
+    emi_interval_days: u64,
+    emi_index: u8,
+) -> Option<u64> {
+    first_repayment_last_date_timestamp.checked_add(
+        emi_interval_days
+            .checked_mul(NUM_SECONDS_IN_ONE_DAY)?
+            .checked_mul(emi_index as u64)?,
+    )
+}
+
+fn period_interest_rate_scaled(annual_interest_rate_bps: u16, emi_interval_days: u64) -> Option<u128> {
+    (annual_interest_rate_bps as u128)
+        .checked_mul(RATE_SCALE)?
+        .checked_mul(emi_interval_days as u128)?
+        .checked_div(BASIS_POINTS.checked_mul(DAYS_IN_ONE_YEAR)?)
+}
+
+pub struct LoanTerms {
+    pub amortization_type: AmortizationType,
+    pub total_loan_amount: u64,
+    pub annual_interest_rate_bps: u16,
+    pub number_of_emis: u8,
+    pub emi_interval_days: u64,
+}
+
+impl LoanTerms {
+    // total interest of a flat rate loan
+    pub fn calculate_flat_total_interest(&self) -> Option<u64> {
+        (self.total_loan_amount as u128)
+            .checked_mul(self.annual_interest_rate_bps as u128)?
+            .checked_mul(self.number_of_emis as u128)?
+            .checked_mul(self.emi_interval_days as u128)?
+            .checked_div(BASIS_POINTS.checked_mul(DAYS_IN_ONE_YEAR)?)?
+            .try_into()
+            .ok()
+    }
+
+    // amount borrower has to pay on every emi, rounded up so that loan is always repaid in number_of_emis
+    pub fn calculate_emi_amount(&self) -> Option<u64> {
+        if self.number_of_emis == 0 {
+            return None;
+        }
+        let emi_amount: u128 = match self.amortization_type {
+            AmortizationType::Flat => div_ceil(
+                (self.total_loan_amount as u128)
+                    .checked_add(self.calculate_flat_total_interest()? as u128)?,
+                self.number_of_emis as u128,
+            )?,
+            AmortizationType::ReducingBalance => {
+                let rate = period_interest_rate_scaled(
+                    self.annual_interest_rate_bps,
+                    self.emi_interval_days,
+                )?;
+                if rate == 0 {
+                    div_ceil(self.total_loan_amount as u128, self.number_of_emis as u128)?
+                } else {
+                    // emi = P * r * (1 + r)^n / ((1 + r)^n - 1)
+                    let mut growth_factor: u128 = RATE_SCALE;
+                    for _ in 0..self.number_of_emis {
+                        growth_factor = growth_factor
+                            .checked_mul(RATE_SCALE.checked_add(rate)?)?
+                            .checked_div(RATE_SCALE)?;
+                    }
+                    let period_interest = (self.total_loan_amount as u128)
+                        .checked_mul(rate)?
+                        .checked_div(RATE_SCALE)?;
+                    div_ceil(
+                        period_interest.checked_mul(growth_factor)?,
+                        growth_factor.checked_sub(RATE_SCALE)?,
+                    )?
+                }
+            }
+        };
+        emi_amount.try_into().ok()
+    }
+
+    // interest borrower owes on the next payment, it is paid before any principal.
+    // Flat loans owe an equal part of total interest per emi, reducing balance loans owe one period of interest on outstanding principal
+    pub fn calculate_interest_due(
+        &self,
+        principal_repaid: u64,
+        interest_paid: u64,
+        number_of_emis_paid: u8,
+    ) -> Option<u64> {
+        let outstanding_principal = self.total_loan_amount.checked_sub(principal_repaid)?;
+        match self.amortization_type {
+            AmortizationType::Flat => {
+                if self.number_of_emis == 0 {
+                    return None;
+                }
+                // if principal is repaid early, all of remaining flat interest is due at once
+                let number_of_emis_due = if outstanding_principal == 0 {
+                    self.number_of_emis
+                } else {
+                    number_of_emis_paid
+                        .saturating_add(1)
+                        .min(self.number_of_emis)
+                };
+                let interest_due_till_now: u64 = (self.calculate_flat_total_interest()? as u128)
+                    .checked_mul(number_of_emis_due as u128)?
+                    .checked_div(self.number_of_emis as u128)?
+                    .try_into()
+                    .ok()?;
+                Some(interest_due_till_now.saturating_sub(interest_paid))
+            }
+            AmortizationType::ReducingBalance => {
+                let rate = period_interest_rate_scaled(
+                    self.annual_interest_rate_bps,
+                    self.emi_interval_days,
+                )?;
+                (outstanding_principal as u128)
+                    .checked_mul(rate)?
+                    .checked_div(RATE_SCALE)?
+                    .try_into()
+                    .ok()
+            }
+        }
+    }
+}
+
+// splits a payment into (principal, interest) parts, interest due is paid first
+pub fn split_emi_payment(
+    payment_amount: u64,
+    interest_due: u64,
+    outstanding_principal: u64,
+) -> Option<(u64, u64)> {
+    let interest_amount = payment_amount.min(interest_due);
+    let principal_amount = payment_amount.checked_sub(interest_amount)?;
+    if principal_amount > outstanding_principal {
+        return None;
+    }
+    Some((principal_amount, interest_amount))
+}
+
+fn div_ceil(numerator: u128, denominator: u128) -> Option<u128> {
+    numerator
+        .checked_add(denominator.checked_sub(1)?)?
+        .checked_div(denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loan_terms(
+        amortization_type: AmortizationType,
+        total_loan_amount: u64,
+        annual_interest_rate_bps: u16,
+        number_of_emis: u8,
+        emi_interval_days: u64,
+    ) -> LoanTerms {
+        LoanTerms {
+            amortization_type,
+            total_loan_amount,
+            annual_interest_rate_bps,
+            number_of_emis,
+            emi_interval_days,
+        }
+    }
+
+    #[test]
+    fn test_flat_emi_amount() {
+        // 12% a year on 1_200_000 for 12 emis of 30 days, 1_200_000 * 0.12 * 360 / 365 = 142_027 interest
+        let terms = loan_terms(AmortizationType::Flat, 1_200_000, 1_200, 12, 30);
+        assert_eq!(terms.calculate_flat_total_interest(), Some(142_027));
+        // (1_200_000 + 142_027) / 12 rounded up
+        assert_eq!(terms.calculate_emi_amount(), Some(111_836));
+    }
+
+    #[test]
+    fn test_reducing_balance_emi_amount() {
+        // 10% per yearly emi, 1_000_000 * 0.1 * 1.1^2 / (1.1^2 - 1) = 576_190.47
+        let terms = loan_terms(AmortizationType::ReducingBalance, 1_000_000, 1_000, 2, 365);
+        assert_eq!(terms.calculate_emi_amount(), Some(576_191));
+        assert_eq!(terms.calculate_interest_due(0, 0, 0), Some(100_000));
+        assert_eq!(terms.calculate_interest_due(476_191, 100_000, 1), Some(52_380));
+    }
+
+    #[test]
+    fn test_zero_interest_rate() {
+        let terms = loan_terms(AmortizationType::Flat, 1_000, 0, 3, 30);
+        assert_eq!(terms.calculate_flat_total_interest(), Some(0));
+        assert_eq!(terms.calculate_emi_amount(), Some(334));
+        assert_eq!(terms.calculate_interest_due(0, 0, 0), Some(0));
+
+        let terms = loan_terms(AmortizationType::ReducingBalance, 1_000, 0, 3, 30);
+        assert_eq!(terms.calculate_emi_amount(), Some(334));
+        assert_eq!(terms.calculate_interest_due(0, 0, 0), Some(0));
+    }
+
+    #[test]
+    fn test_flat_interest_due() {
+        let terms = loan_terms(AmortizationType::Flat, 1_200_000, 1_200, 12, 30);
+        // interest of first emi is 142_027 / 12
+        assert_eq!(terms.calculate_interest_due(0, 0, 0), Some(11_835));
+        assert_eq!(terms.calculate_interest_due(100_000, 11_835, 1), Some(11_836));
+        // principal repaid early, all of remaining interest is due at once
+        assert_eq!(terms.calculate_interest_due(1_200_000, 11_835, 1), Some(130_192));
+    }
+
+    #[test]
+    fn test_split_emi_payment() {
+        assert_eq!(split_emi_payment(600, 100, 1_000), Some((500, 100)));
+        assert_eq!(split_emi_payment(50, 100, 1_000), Some((0, 50)));
+        assert_eq!(split_emi_payment(1_100, 100, 1_000), Some((1_000, 100)));
+        // payment above interest due and outstanding principal is rejected
+        assert_eq!(split_emi_payment(1_101, 100, 1_000), None);
+    }
+}
@@ -0,0 +1,123 @@
+// Late payment detection, late fees and credit score changes. Scores use 9 decimals like DassiCoin, so 1 point is 1_000_000_000,
+// score always stays between 0 and 1000 points. Initial score, grace period and late fee are read from ProtocolConfig.
+
+pub const ONE_SCORE_POINT: u64 = 1_000_000_000;
+pub const MAX_CREDIT_SCORE: u64 = 1000 * ONE_SCORE_POINT;
+pub const ON_TIME_EMI_SCORE_REWARD: u64 = 5 * ONE_SCORE_POINT;
+// late emi costs 10 points plus 1 point for every full day after grace period, but not more than 50 points
+pub const LATE_EMI_SCORE_PENALTY: u64 = 10 * ONE_SCORE_POINT;
+pub const LATE_EMI_SCORE_PENALTY_PER_DAY: u64 = ONE_SCORE_POINT;
+pub const MAX_LATE_EMI_SCORE_PENALTY: u64 = 50 * ONE_SCORE_POINT;
+pub const DEFAULT_SCORE_PENALTY: u64 = 250 * ONE_SCORE_POINT;
+pub const GUARANTOR_DEFAULT_SCORE_PENALTY: u64 = 100 * ONE_SCORE_POINT;
+
+// loan can be declared defaulted once 3 emis are late
+pub const MISSED_EMIS_TO_DECLARE_DEFAULT: u8 = 3;
+
+const BASIS_POINTS: u128 = 10_000;
+const NUM_SECONDS_IN_ONE_DAY: u64 = 86400;
+
+// number of full days payment is late after grace period, None if payment is not late
+pub fn calculate_days_late(emi_due_timestamp: u64, grace_period_days: u64, now: u64) -> Option<u64> {
+    let last_date_timestamp =
+        emi_due_timestamp.saturating_add(grace_period_days.saturating_mul(NUM_SECONDS_IN_ONE_DAY));
+    if now <= last_date_timestamp {
+        return None;
+    }
+    Some((now - last_date_timestamp) / NUM_SECONDS_IN_ONE_DAY)
+}
+
+pub fn calculate_score_after_on_time_emi(score: u64) -> u64 {
+    score
+        .saturating_add(ON_TIME_EMI_SCORE_REWARD)
+        .min(MAX_CREDIT_SCORE)
+}
+
+pub fn calculate_score_after_late_emi(score: u64, days_late: u64) -> u64 {
+    let penalty = LATE_EMI_SCORE_PENALTY
+        .saturating_add(LATE_EMI_SCORE_PENALTY_PER_DAY.saturating_mul(days_late))
+        .min(MAX_LATE_EMI_SCORE_PENALTY);
+    score.saturating_sub(penalty)
+}
+
+pub fn calculate_score_after_default(score: u64) -> u64 {
+    score.saturating_sub(DEFAULT_SCORE_PENALTY)
+}
+
+pub fn calculate_approval_score_after_default(score: u64) -> u64 {
+    score.saturating_sub(GUARANTOR_DEFAULT_SCORE_PENALTY)
+}
+
+// Splits amount paid towards an overdue emi into (amount applied to emi, late fee). If amount covers whole overdue amount plus
+// its fee, only that much is used, otherwise everything paid is split between emi and fee
+pub fn split_overdue_payment(
+    amount_paid: u64,
+    overdue_amount: u64,
+    late_fee_bps: u16,
+) -> Option<(u64, u64)> {
+    let max_amount_applied: u64 = (amount_paid as u128)
+        .checked_mul(BASIS_POINTS)?
+        .checked_div(BASIS_POINTS.checked_add(late_fee_bps as u128)?)? as u64;
+    if overdue_amount <= max_amount_applied {
+        let late_fee = (overdue_amount as u128)
+            .checked_mul(late_fee_bps as u128)?
+            .checked_div(BASIS_POINTS)? as u64;
+        Some((overdue_amount, late_fee))
+    } else {
+        Some((max_amount_applied, amount_paid.checked_sub(max_amount_applied)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUE_TIMESTAMP: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_days_late_after_grace_period() {
+        let last_date_timestamp = DUE_TIMESTAMP + 5 * NUM_SECONDS_IN_ONE_DAY;
+        assert_eq!(calculate_days_late(DUE_TIMESTAMP, 5, DUE_TIMESTAMP), None);
+        assert_eq!(calculate_days_late(DUE_TIMESTAMP, 5, last_date_timestamp), None);
+        assert_eq!(calculate_days_late(DUE_TIMESTAMP, 5, last_date_timestamp + 1), Some(0));
+        assert_eq!(
+            calculate_days_late(DUE_TIMESTAMP, 5, last_date_timestamp + 3 * NUM_SECONDS_IN_ONE_DAY),
+            Some(3)
+        );
+        assert_eq!(calculate_days_late(DUE_TIMESTAMP, 0, DUE_TIMESTAMP + 1), Some(0));
+    }
+
+    #[test]
+    fn test_scores_stay_between_zero_and_max() {
+        assert_eq!(
+            calculate_score_after_on_time_emi(MAX_CREDIT_SCORE - ONE_SCORE_POINT),
+            MAX_CREDIT_SCORE
+        );
+        assert_eq!(calculate_score_after_on_time_emi(MAX_CREDIT_SCORE), MAX_CREDIT_SCORE);
+        assert_eq!(calculate_score_after_late_emi(3 * ONE_SCORE_POINT, 0), 0);
+        assert_eq!(calculate_score_after_default(100 * ONE_SCORE_POINT), 0);
+        assert_eq!(calculate_approval_score_after_default(50 * ONE_SCORE_POINT), 0);
+    }
+
+    #[test]
+    fn test_late_emi_penalty_is_capped() {
+        let score = 500 * ONE_SCORE_POINT;
+        assert_eq!(calculate_score_after_late_emi(score, 0), score - LATE_EMI_SCORE_PENALTY);
+        assert_eq!(calculate_score_after_late_emi(score, 5), score - 15 * ONE_SCORE_POINT);
+        assert_eq!(calculate_score_after_late_emi(score, 40), score - MAX_LATE_EMI_SCORE_PENALTY);
+        assert_eq!(
+            calculate_score_after_late_emi(score, u64::MAX),
+            score - MAX_LATE_EMI_SCORE_PENALTY
+        );
+    }
+
+    #[test]
+    fn test_split_overdue_payment() {
+        // amount covers overdue amount and its 2% fee, the rest is not used
+        assert_eq!(split_overdue_payment(1_500, 1_000, 200), Some((1_000, 20)));
+        assert_eq!(split_overdue_payment(1_020, 1_000, 200), Some((1_000, 20)));
+        // partial payment is split between emi and fee in the same ratio
+        assert_eq!(split_overdue_payment(510, 1_000, 200), Some((500, 10)));
+        assert_eq!(split_overdue_payment(510, 1_000, 0), Some((510, 0)));
+    }
+}
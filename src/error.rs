@@ -82,6 +82,141 @@ pub enum DassiError {
     ///Data Size Not Matched
     #[error("Data Size Does Not Matched")]
     DataSizeNotMatched,
+    /// Fundraising Period Not Expired
+    #[error("Fundraising Period Not Expired")]
+    FundraisingPeriodNotExpired,
+    /// Funds Already Returned To Lenders
+    #[error("Funds Already Returned To Lenders")]
+    FundsAlreadyReturnedToLenders,
+    /// Lender Balance Still Owed
+    #[error("Lender Balance Still Owed")]
+    LenderBalanceStillOwed,
+    /// Invalid Loan Parameters
+    #[error("Invalid Loan Parameters")]
+    InvalidLoanParameters,
+    /// Loan Not Overdue
+    #[error("Loan Not Overdue")]
+    LoanNotOverdue,
+    /// Loan Already Defaulted
+    #[error("Loan Already Defaulted")]
+    LoanAlreadyDefaulted,
+    /// Loan Not Defaulted
+    #[error("Loan Not Defaulted")]
+    LoanNotDefaulted,
+    /// Guarantor Account Mismatched
+    #[error("Guarantor Account Mismatched")]
+    GuarantorAccountMismatched,
+    /// Insufficient Guarantor Stake
+    #[error("Insufficient Guarantor Stake")]
+    InsufficientGuarantorStake,
+    /// Guarantor Stake Vault Does Not Matched
+    #[error("Guarantor Stake Vault Does Not Matched")]
+    GuarantorStakeVaultDoesNotMatched,
+    /// Unexpected Loan Status
+    #[error("Unexpected Loan Status")]
+    UnexpectedLoanStatus,
+    /// Invalid Loan Status Transition
+    #[error("Invalid Loan Status Transition")]
+    InvalidLoanStatusTransition,
+    /// Unsupported Instruction Version
+    #[error("Unsupported Instruction Version")]
+    UnsupportedInstructionVersion,
+    /// Instruction Data Has Trailing Bytes
+    #[error("Instruction Data Has Trailing Bytes")]
+    InstructionDataHasTrailingBytes,
+    /// Invalid Config Account
+    #[error("Invalid Config Account")]
+    InvalidConfigAccount,
+    /// Config Already Initialized
+    #[error("Config Already Initialized")]
+    ConfigAlreadyInitialized,
+    /// Invalid Config Parameters
+    #[error("Invalid Config Parameters")]
+    InvalidConfigParameters,
+    /// Mint Does Not Matched
+    #[error("Mint Does Not Matched")]
+    MintDoesNotMatched,
+    /// Pending Admin Does Not Matched
+    #[error("Pending Admin Does Not Matched")]
+    PendingAdminDoesNotMatched,
+    /// Risk Manager Does Not Matched
+    #[error("Risk Manager Does Not Matched")]
+    RiskManagerDoesNotMatched,
+    /// Pauser Does Not Matched
+    #[error("Pauser Does Not Matched")]
+    PauserDoesNotMatched,
+    /// Invalid Pause Flags
+    #[error("Invalid Pause Flags")]
+    InvalidPauseFlags,
+    /// Operation Paused
+    #[error("Operation Paused")]
+    OperationPaused,
+    /// Invalid Token Account
+    #[error("Invalid Token Account")]
+    InvalidTokenAccount,
+    /// Token Account Owner Does Not Matched
+    #[error("Token Account Owner Does Not Matched")]
+    TokenAccountOwnerDoesNotMatched,
+    /// Airdrop Vault Account Does Not Matched
+    #[error("Airdrop Vault Account Does Not Matched")]
+    AirdropVaultAccountDoesNotMatched,
+    /// Lender Account Already Initialized
+    #[error("Lender Account Already Initialized")]
+    LenderAccountAlreadyInitialized,
+    /// Lender Account Mismatched
+    #[error("Lender Account Mismatched")]
+    LenderAccountMismatched,
+    /// Loan Lenders Limit Reached
+    #[error("Loan Lenders Limit Reached")]
+    LoanLendersLimitReached,
+    /// Repayments Not Credited To Lenders
+    #[error("Repayments Not Credited To Lenders")]
+    RepaymentsNotCreditedToLenders,
+    /// No Repayments To Credit
+    #[error("No Repayments To Credit")]
+    NoRepaymentsToCredit,
+    /// Invalid Lender Index Input
+    #[error("Invalid Lender Index Input")]
+    InvalidLenderIndexInput,
+    /// Lending Amount Not Multiple Of Unit
+    #[error("Lending Amount Not Multiple Of Unit")]
+    LendingAmountNotMultipleOfUnit,
+    /// Insufficient Free Balance
+    #[error("Insufficient Free Balance")]
+    InsufficientFreeBalance,
+    /// Invalid Lending Strategy
+    #[error("Invalid Lending Strategy")]
+    InvalidLendingStrategy,
+    /// Lending Strategy Not Enabled
+    #[error("Lending Strategy Not Enabled")]
+    LendingStrategyNotEnabled,
+    /// Loan Does Not Match Lending Strategy
+    #[error("Loan Does Not Match Lending Strategy")]
+    LoanDoesNotMatchLendingStrategy,
+    /// Loan Mint Not Accepted
+    #[error("Loan Mint Not Accepted")]
+    LoanMintNotAccepted,
+    /// Guarantor Stake Not In Loan Mint
+    #[error("Guarantor Stake Not In Loan Mint")]
+    GuarantorStakeNotInLoanMint,
+    /// Config Mints Can Not Be Updated
+    #[error("Config Mints Can Not Be Updated")]
+    ConfigMintsCanNotBeUpdated,
+    /// Loan Mint Already Accepted
+    #[error("Loan Mint Already Accepted")]
+    LoanMintAlreadyAccepted,
+    /// Accepted Loan Mints Full
+    #[error("Accepted Loan Mints Full")]
+    AcceptedLoanMintsFull,
+    /// Legacy Vault Already Migrated
+    #[error("Legacy Vault Already Migrated")]
+    LegacyVaultAlreadyMigrated,
+    /// Legacy Vault Not Migrated
+    #[error("Legacy Vault Not Migrated")]
+    LegacyVaultNotMigrated,
+    /// Guarantor Stake Account Already Initialized
+    #[error("Guarantor Stake Account Already Initialized")]
+    GuarantorStakeAccountAlreadyInitialized,
 }
 
 impl From<DassiError> for ProgramError {
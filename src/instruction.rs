@@ -1,73 +1,116 @@
-use crate::error::DassiError::InvalidInstruction;
+use crate::error::DassiError::{
+    InstructionDataHasTrailingBytes, InvalidInstruction, UnsupportedInstructionVersion,
+};
+use crate::state::ProtocolConfigParams;
+use crate::utils::{
+    get_lender_position_address, get_lender_storage_address, get_mint_vault_address,
+    get_protocol_config_address,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::msg;
 use solana_program::program_error::ProgramError;
-use std::convert::TryInto;
+use solana_program::pubkey::Pubkey;
+use solana_program::{system_program, sysvar};
 
+// bumped whenever layout of any instruction changes, so old clients get a clear error instead of misread data
+pub const INSTRUCTION_VERSION: u8 = 3;
+
+// Variant order is the instruction tag, new instructions must only be added at the end
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
 pub enum DassiInstruction {
+    /// amount_to_lend_input must be a multiple of 10 tokens of loan mint. If loan needs less than that, only the amount loan still
+    /// needs is taken from lender
+    ///
     /// Accounts Expected:
     ///
-    /// 0. `[signer]` Lender Main Account
-    /// 1. `[writable]` Lender DassiCoin ATA To Debit
-    /// 2. `[writable]` DassiCoin Vault Account
+    /// 0. `[signer, writable]` Lender Main Account, pays rent of lender position account when it is created
+    /// 1. `[writable]` Lender ATA Of Loan Mint To Debit
+    /// 2. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
     /// 3. `[]` Token Program
     /// 4. `[writable]` Loan Info Storage Account
-    /// 5. `[writable]` Lenders Account Data Storage Account
-    LendToBorrower {
-        amount_to_lend_input: u64,
-        lender_id_input: u32,
-    },
+    /// 5. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, loan mint)
+    /// 6. `[writable]` Lender Position Account (pda: "DassiFinancePosition", lender main account, loan info account)
+    /// 7. `[]` System Program
+    /// 8. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    LendToBorrower { amount_to_lend_input: u64 },
 
     /// 0. `[signer]` Lender Main Account
-    /// 1. `[writable]` Lender DassiCoin ATA To Credit
-    /// 2. `[writable]` DassiCoin Vault Account
-    /// 3. `[writable]` Lenders Account Data Storage Account
+    /// 1. `[writable]` Lender ATA Of Lender Account Mint To Credit
+    /// 2. `[writable]` Vault Account Of Lender Account Mint (pda: "DassiFinanceVault", mint)
+    /// 3. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, mint)
     /// 4. `[]` Token Program
     /// 5. `[]` The PDA Account ("DassiFinance")
-    WithdrawLenderFreeWalletFunds { lender_id_input: u32 },
+    /// 6. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    WithdrawLenderFreeWalletFunds {},
 
     /// 0. `[signer]` Borrower Main Account
-    /// 1. `[writable]` Borrower DassiCoin ATA To Credit
-    /// 2. `[writable]` DassiCoin Vault Account
+    /// 1. `[writable]` Borrower ATA Of Loan Mint To Credit
+    /// 2. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
     /// 3. `[]` Token Program
     /// 4. `[writable]` Loan Info Storage Account
     /// 5. `[]` The PDA Account ("DassiFinance")
+    /// 6. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     WithdrawCollectedLoanFunds {},
 
-    /// 0. `[signer]` Initializer Account
-    /// 1. `[writable]` DassiCoin Vault Account
-    /// 2. `[]` Token Program
+    /// Deprecated, vault of every loan mint is created at its pda by InitializeMintVault and balance of vault deployed
+    /// before is moved there by MigrateLegacyVault. Kept so that tags of later instructions don't change, it always fails
     TransferDassiVaultAccountOwnership {},
 
-    /// 0. `[signer]` Fee Payer
-    /// 1. `[writable]` Lenders Account Data Storage Account
+    /// Deprecated, every lender has its own account created by InitializeLenderAccount. Kept so that tags of later
+    /// instructions don't change, it always fails
     InitializeLendersStorageAccount {},
 
-    /// 0. `[signer]` Guarantor Main Account
-    /// 1. `[writable]` Guarantor Storage Account (seed: "DassiFinanceGuarantor")
-    InitializeGuarantorAccount {},
-
     /// 0. `[signer]` Borrower Main Account
-    /// 1. `[writable]` Borrower Storage Account (seed: "DassiFinanceBorrower")
+    /// 1. `[writable]` Borrower Storage Account (pda: "DassiFinanceBorrower", borrower main account)
+    /// 2. `[signer, writable]` Payer Account, pays rent of the storage account
+    /// 3. `[]` System Program
+    /// 4. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     InitializeBorrowerAccount {},
 
+    /// 0. `[signer]` Guarantor Main Account
+    /// 1. `[writable]` Guarantor Storage Account (pda: "DassiFinanceGuarantor", guarantor main account)
+    /// 2. `[signer, writable]` Payer Account, pays rent of the storage account
+    /// 3. `[]` System Program
+    /// 4. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    InitializeGuarantorAccount {},
+
     /// 0. `[signer]` Borrower Main Account
-    /// 1. `[writable]` Borrower Dassi ATA To Debit
-    /// 2. `[writable]` DassiCoin Vault Account
-    /// 3. `[writable]` Borrower Storage Account (seed: "DassiFinanceBorrower")
+    /// 1. `[writable]` Borrower ATA Of Loan Mint To Debit
+    /// 2. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
+    /// 3. `[writable]` Borrower Storage Account (pda: "DassiFinanceBorrower", borrower main account)
     /// 4. `[]` Token Program
     /// 5. `[writable]` Loan Info Storage Account
-    /// 6. `[writable]` Lenders Account Data Storage Account
+    /// 6. `[writable]` Guarantor Stake Account Of Loan Mint (pda: "DassiFinanceGuarantorStake", guarantor main account, loan
+    ///    mint), needed only when last emi is paid and guarantor has stake locked for the loan
+    /// 7. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     PayEMIforLoan { emi_amount_to_pay_input: u64 },
 
+    /// amortization_type_input: 0 = flat interest, 1 = reducing balance interest
+    /// repayment_interval_type_input: 0 = monthly emis, 1 = weekly emis, 2 = every custom_repayment_interval_days_input days
+    /// Guarantor stake coverage of config is locked from guarantor stake in the loan mint. Loans are read only in the layout
+    /// created here, loans of the deployed program must be settled before upgrading (see state::LOAN_INFO_ACC_DATA_SIZE)
+    ///
     /// 0. `[signer]` Guarantor Main Account
     /// 1. `[]` Borrower Main Account
-    /// 2. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Loan Info Storage Account (pda: "DassiFinanceLoan", borrower main account, borrower next loan nonce)
     /// 3. `[writable]` Borrower Storage Account
+    /// 4. `[]` Guarantor Storage Account
+    /// 5. `[]` Loan Mint Account, one of accepted loan mints of config, loan is raised and repaid in it
+    /// 6. `[signer, writable]` Payer Account, pays rent of the loan info storage account
+    /// 7. `[]` System Program
+    /// 8. `[writable]` Guarantor Stake Account Of Loan Mint (pda: "DassiFinanceGuarantorStake", guarantor main account, loan
+    ///    mint), needed only when guarantor stake coverage of config is above 0
+    /// 9. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     InitializeLoanInfoAccount {
         num_days_left_for_first_repayment_input: u16,
         num_emis_needed_to_repay_the_loan_input: u16,
         num_days_for_fundraising_input: u16,
         total_loan_amount_input: u64,
+        annual_interest_rate_bps_input: u16,
+        amortization_type_input: u8,
+        repayment_interval_type_input: u8,
+        custom_repayment_interval_days_input: u16,
     },
 
     /// 0. `[signer]` Fee Payer ( Airdrop User )
@@ -76,87 +119,1095 @@ pub enum DassiInstruction {
     /// 3. `[writable]` Airdrop Vault DassiCoin Account
     /// 4. `[]` Token Program
     /// 5. `[]` The PDA Account ("DassiFinanceAirdrop")
+    /// 6. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     AirdropUsersWithDassiTestCoins {},
 
     /// 0. `[signer]` Initializer Account
     /// 1. `[writable]` DassiCoin Airdrop Vault Account (pda: "DassiFinanceAirdrop")
-    /// 3. `[]` Token Program
+    /// 2. `[]` Token Program
+    /// 3. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     TransferAirdropVaultAccountOwnership {},
 
-
+    /// Credits lent amount back to lenders of a loan whose fundraising period expired, num_accounts_input lenders per call
+    ///
+    /// 0. `[signer]` Fee Payer
+    /// 1. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Lender Storage Account and Lender Position Account of each lender refunded in this call, in the order
+    ///    lenders lent
+    /// 3. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     ReturnFundsToLenders { num_accounts_input: u16 },
 
-  
+    /// Closes a fully repaid or fully refunded loan, borrower can take a new loan afterwards
+    ///
+    /// 0. `[signer]` Borrower Main Account
+    /// 1. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Borrower Storage Account
+    /// 3. `[writable]` Rent Recipient Account, can't be the loan info storage account
+    /// 4. `[writable]` Optional Guarantor Stake Account Of Loan Mint, only when guarantor stake is still locked in loan,
+    ///    which is the case for a refunded loan with a guarantor
+    /// 5. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
     CloseLoanInfoAccount {},
-}
 
+    /// Declares a loan defaulted after borrower missed enough emis, can be called by anyone
+    ///
+    /// 0. `[signer]` Fee Payer
+    /// 1. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Borrower Storage Account
+    /// 3. `[writable]` Guarantor Storage Account
+    /// 4. `[writable]` Guarantor Stake Account Of Loan Mint (pda: "DassiFinanceGuarantorStake", guarantor main account, loan
+    ///    mint), needed only when guarantor has stake locked for the loan
+    /// 5. `[writable]` Guarantor Stake Vault Account (pda: "DassiFinanceGuarantorStakeVault", guarantor stake account),
+    ///    this and accounts below are needed only when locked stake is slashed, that is outstanding principal is above 0
+    /// 6. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
+    /// 7. `[]` Token Program
+    /// 8. `[]` The PDA Account ("DassiFinance")
+    /// 9. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    MarkLoanDefaulted {},
+
+    /// 0. `[signer]` Guarantor Main Account
+    /// 1. `[writable]` Guarantor ATA Of Loan Mint To Debit
+    /// 2. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
+    /// 3. `[]` Token Program
+    /// 4. `[writable]` Loan Info Storage Account
+    /// 5. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    PayDefaultedLoanAsGuarantor { amount_to_pay_input: u64 },
+
+    /// Creates guarantor stake account of a mint and its stake vault, guarantor backs loans in that mint with it
+    ///
+    /// 0. `[signer, writable]` Guarantor Main Account, pays rent of both accounts
+    /// 1. `[]` Guarantor Storage Account
+    /// 2. `[writable]` Guarantor Stake Account (pda: "DassiFinanceGuarantorStake", guarantor main account, mint)
+    /// 3. `[writable]` Guarantor Stake Vault Account (pda: "DassiFinanceGuarantorStakeVault", guarantor stake account)
+    /// 4. `[]` Mint Account, one of accepted loan mints of config
+    /// 5. `[]` The PDA Account ("DassiFinance")
+    /// 6. `[]` System Program
+    /// 7. `[]` Token Program
+    /// 8. `[]` Rent Sysvar
+    /// 9. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    InitializeGuarantorStakeVault {},
+
+    /// 0. `[signer]` Guarantor Main Account
+    /// 1. `[writable]` Guarantor ATA Of Stake Mint To Debit
+    /// 2. `[writable]` Guarantor Stake Vault Account
+    /// 3. `[]` Token Program
+    /// 4. `[writable]` Guarantor Stake Account
+    /// 5. `[]` Stake Mint Account
+    /// 6. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    StakeGuarantorCollateral { amount_to_stake_input: u64 },
+
+    /// 0. `[signer]` Guarantor Main Account
+    /// 1. `[writable]` Guarantor ATA Of Stake Mint To Credit
+    /// 2. `[writable]` Guarantor Stake Vault Account
+    /// 3. `[]` Token Program
+    /// 4. `[writable]` Guarantor Stake Account
+    /// 5. `[]` The PDA Account ("DassiFinance")
+    /// 6. `[]` Stake Mint Account
+    /// 7. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    UnstakeGuarantorCollateral { amount_to_unstake_input: u64 },
+
+    /// Creates protocol config account, signer must be the admin key compiled into the program (utils::get_admin_pubkey). That
+    /// key only bootstraps the config: it is stored as admin of the config, every later admin check reads the stored admin,
+    /// which can be handed over with ProposeAdmin and AcceptAdmin
+    ///
+    /// 0. `[signer, writable]` Admin Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    /// 2. `[]` System Program
+    InitializeConfig { params_input: ProtocolConfigParams },
+
+    /// Updates risk parameters of protocol config. accepted_mint and accepted_loan_mints of params_input must be the ones
+    /// config already has, DassiCoin mint never changes and admin adds loan mints with AddAcceptedLoanMint
+    ///
+    /// 0. `[signer]` Risk Manager Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    UpdateConfig { params_input: ProtocolConfigParams },
+
+    /// Proposes new admin, it becomes admin only after accepting. Proposing default pubkey cancels pending handover
+    ///
+    /// 0. `[signer]` Admin Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    ProposeAdmin { new_admin_input: Pubkey },
+
+    /// 0. `[signer]` Pending Admin Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    AcceptAdmin {},
+
+    /// 0. `[signer]` Admin Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    SetRoles {
+        pauser_input: Pubkey,
+        risk_manager_input: Pubkey,
+    },
+
+    /// Sets given pause flags (state::PAUSE_*), can be signed by pauser or admin
+    ///
+    /// 0. `[signer]` Pauser Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    PauseOperations { operations_input: u8 },
+
+    /// Clears given pause flags, only admin can resume paused operations
+    ///
+    /// 0. `[signer]` Admin Account
+    /// 1. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    UnpauseOperations { operations_input: u8 },
+
+    /// Lender has one account per mint, it holds his free balance and lending totals in that mint
+    ///
+    /// 0. `[signer]` Lender Main Account
+    /// 1. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, mint)
+    /// 2. `[]` Mint Account, one of accepted loan mints of config
+    /// 3. `[signer, writable]` Payer Account, pays rent of the storage account
+    /// 4. `[]` System Program
+    /// 5. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    InitializeLenderAccount {},
+
+    /// Moves lender in slot lender_id_input of legacy lenders storage account to the lender's own account, creating it if
+    /// needed. Slot is cleared afterwards, so it can be migrated only once. Can be called by anyone, once MigrateLegacyVault
+    /// moved legacy vault balance that backs the migrated balances
+    ///
+    /// 0. `[signer, writable]` Payer Account, pays rent of the lender storage account when it is created
+    /// 1. `[writable]` Lenders Account Data Storage Account
+    /// 2. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account stored in the slot,
+    ///    DassiCoin mint)
+    /// 3. `[]` System Program
+    /// 4. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    MigrateLenderAccount { lender_id_input: u32 },
+
+    /// Credits repayments of a loan to its lenders pro rata, num_accounts_input lenders per call. Can be called by anyone
+    ///
+    /// 0. `[signer]` Fee Payer
+    /// 1. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Lender Storage Account and Lender Position Account of each lender credited in this call, in the order
+    ///    lenders lent
+    /// 3. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    CreditRepaymentsToLenders { num_accounts_input: u16 },
+
+    /// Returns amount_to_cancel_input of the amount lent in slot lender_index_input of loan back to lender, only while loan
+    /// is raising funds. Slot must belong to the signer, otherwise instruction fails. A partial cancel must be a multiple of
+    /// lending unit and leave at least min lending amount in the slot. A slot whose whole amount is cancelled is removed and
+    /// last slot of the loan moves to its index, so slot indexes read before a whole slot cancel must be read again from loan
+    ///
+    /// 0. `[signer]` Lender Main Account
+    /// 1. `[writable]` Lender ATA Of Loan Mint To Credit
+    /// 2. `[writable]` Vault Account Of Loan Mint (pda: "DassiFinanceVault", loan mint)
+    /// 3. `[]` Token Program
+    /// 4. `[writable]` Loan Info Storage Account
+    /// 5. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, loan mint)
+    /// 6. `[writable]` Lender Position Account (pda: "DassiFinancePosition", lender main account, loan info account)
+    /// 7. `[]` The PDA Account ("DassiFinance")
+    /// 8. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    CancelLendingCommitment {
+        lender_index_input: u8,
+        amount_to_cancel_input: u64,
+    },
+
+    /// Lends from lender's free balance, which holds credited repayments and refunds, without moving tokens out of vault of
+    /// loan mint. Same rules as LendToBorrower apply to amount_to_lend_input
+    ///
+    /// 0. `[signer, writable]` Lender Main Account, pays rent of lender position account when it is created
+    /// 1. `[writable]` Loan Info Storage Account
+    /// 2. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, loan mint)
+    /// 3. `[writable]` Lender Position Account (pda: "DassiFinancePosition", lender main account, loan info account)
+    /// 4. `[]` System Program
+    /// 5. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    RelendFromFreeBalance { amount_to_lend_input: u64 },
+
+    /// Sets lending strategy of lender, AllocateFreeBalanceToLoans lends his free balance to loans matching it. Strategy is
+    /// disabled by setting max_amount_per_loan_input to 0
+    ///
+    /// 0. `[signer]` Lender Main Account
+    /// 1. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, mint)
+    /// 2. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    SetLendingStrategy {
+        max_amount_per_loan_input: u64,
+        min_credit_score_input: u64,
+        min_approval_score_input: u64,
+    },
+
+    /// Lends free balance of a lender with enabled lending strategy to num_loans_input raising loans matching the strategy,
+    /// upto strategy's max amount per loan in each. Can be called by anyone
+    ///
+    /// 0. `[signer, writable]` Fee Payer, pays rent of lender position accounts created
+    /// 1. `[writable]` Lender Storage Account (pda: "DassiFinanceLender", lender main account, mint)
+    /// 2. `[]` System Program
+    /// 3. For each loan, in this order:
+    ///    `[writable]` Loan Info Storage Account
+    ///    `[]` Borrower Storage Account of the loan
+    ///    `[]` Guarantor Storage Account of the loan
+    ///    `[writable]` Lender Position Account (pda: "DassiFinancePosition", lender main account, loan info account)
+    /// 4. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    AllocateFreeBalanceToLoans { num_loans_input: u8 },
+
+    /// Creates vault of an accepted loan mint, owned by "DassiFinance" pda. Loans in the mint can be funded once it exists
+    ///
+    /// 0. `[signer, writable]` Admin Account, pays rent of the vault
+    /// 1. `[writable]` Mint Vault Account (pda: "DassiFinanceVault", mint)
+    /// 2. `[]` Mint Account, one of accepted loan mints of config
+    /// 3. `[]` The PDA Account ("DassiFinance")
+    /// 4. `[]` System Program
+    /// 5. `[]` Token Program
+    /// 6. `[]` Rent Sysvar
+    /// 7. `[]` Protocol Config Account (pda: "DassiFinanceConfig")
+    InitializeMintVault {},
+
+    /// Adds mint to a free slot of accepted loan mints of config. Accepted loan mints are never removed or replaced, loans
+    /// and vaults in them would be stranded
+    ///
+    /// 0. `[signer]` Admin Account
+    /// 1. `[]` Mint Account
+    /// 2. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    AddAcceptedLoanMint {},
+
+    /// Moves whole balance of DassiCoin vault deployed before mint vaults to vault of DassiCoin mint. Can be done only once,
+    /// lenders can be migrated with MigrateLenderAccount afterwards
+    ///
+    /// 0. `[signer]` Admin Account
+    /// 1. `[writable]` Legacy DassiCoin Vault Account, owned by "DassiFinance" pda
+    /// 2. `[writable]` Vault Account Of DassiCoin Mint (pda: "DassiFinanceVault", DassiCoin mint)
+    /// 3. `[]` Token Program
+    /// 4. `[]` The PDA Account ("DassiFinance")
+    /// 5. `[writable]` Protocol Config Account (pda: "DassiFinanceConfig")
+    MigrateLegacyVault {},
+}
 
 impl DassiInstruction {
+    // Instruction data is INSTRUCTION_VERSION byte followed by borsh encoded DassiInstruction, whose first byte is the variant tag.
+    // Data which is empty, has other version, doesn't decode or has bytes left after decoding is rejected
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        msg!("In {}", input.len());
+        let (&version, mut rest) = input.split_first().ok_or(InvalidInstruction)?;
 
-        Ok(match input[0] {
-            0 => Self::LendToBorrower {
-                amount_to_lend_input: Self::unpack_to_u64(&input[1..9])?,
-                lender_id_input: Self::unpack_to_u32(&input[9..13]),
-            },
-            1 => Self::WithdrawLenderFreeWalletFunds {
-                lender_id_input: Self::unpack_to_u32(&input[1..5]),
-            },
-            2 => Self::WithdrawCollectedLoanFunds {},
+        if version != INSTRUCTION_VERSION {
+            msg!("unsupported instruction version {}", version);
+            return Err(UnsupportedInstructionVersion.into());
+        }
 
-            3 => Self::TransferDassiVaultAccountOwnership {},
+        let instruction = Self::deserialize(&mut rest).map_err(|err| {
+            msg!("failed to decode instruction: {}", err);
+            InvalidInstruction
+        })?;
 
-            4 => Self::InitializeLendersStorageAccount {},
+        if !rest.is_empty() {
+            msg!("{} unexpected bytes after instruction data", rest.len());
+            return Err(InstructionDataHasTrailingBytes.into());
+        }
 
-            5 => Self::InitializeBorrowerAccount {},
+        Ok(instruction)
+    }
 
-            6 => Self::InitializeGuarantorAccount {},
+    // inverse of unpack
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = vec![INSTRUCTION_VERSION];
+        buf.extend_from_slice(&self.try_to_vec().unwrap());
+        buf
+    }
+}
 
-            7 => Self::PayEMIforLoan {
-                emi_amount_to_pay_input: Self::unpack_to_u64(&input[1..9])?,
-            },
+// Builders below return instructions with accounts in the order listed on DassiInstruction variants,
+// so clients don't have to assemble instruction data and account metas by hand
 
-            8 => Self::InitializeLoanInfoAccount {
-                num_days_left_for_first_repayment_input: Self::unpack_to_u16(&input[1..3]),
-                num_emis_needed_to_repay_the_loan_input: Self::unpack_to_u16(&input[3..5]),
-                num_days_for_fundraising_input: Self::unpack_to_u16(&input[5..7]),
-                total_loan_amount_input: Self::unpack_to_u64(&input[7..15])?,
-            },
-            9 => Self::AirdropUsersWithDassiTestCoins {},
-            10 => Self::TransferAirdropVaultAccountOwnership {},
+#[allow(clippy::too_many_arguments)]
+pub fn lend_to_borrower(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    lender_loan_mint_account_to_debit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    lender_position_account: &Pubkey,
+    amount_to_lend_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lender_main_account, true),
+            AccountMeta::new(*lender_loan_mint_account_to_debit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new(*lender_position_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::LendToBorrower {
+            amount_to_lend_input,
+        }
+        .pack(),
+    }
+}
 
-            11 => Self::ReturnFundsToLenders {
-                num_accounts_input: Self::unpack_to_u16(&input[1..3]),
-            },
+pub fn withdraw_lender_free_wallet_funds(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    lender_loan_mint_account_to_credit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*lender_main_account, true),
+            AccountMeta::new(*lender_loan_mint_account_to_credit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::WithdrawLenderFreeWalletFunds {}.pack(),
+    }
+}
 
-            12 => Self::CloseLoanInfoAccount {},
+pub fn withdraw_collected_loan_funds(
+    program_id: &Pubkey,
+    borrower_main_account: &Pubkey,
+    borrower_loan_mint_account_to_credit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*borrower_main_account, true),
+            AccountMeta::new(*borrower_loan_mint_account_to_credit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::WithdrawCollectedLoanFunds {}.pack(),
+    }
+}
 
-            _ => return Err(InvalidInstruction.into()),
-        })
+pub fn initialize_guarantor_account(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    guarantor_storage_account: &Pubkey,
+    payer_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*guarantor_main_account, true),
+            AccountMeta::new(*guarantor_storage_account, false),
+            AccountMeta::new(*payer_account, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeGuarantorAccount {}.pack(),
+    }
+}
+
+pub fn initialize_borrower_account(
+    program_id: &Pubkey,
+    borrower_main_account: &Pubkey,
+    borrower_storage_account: &Pubkey,
+    payer_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*borrower_main_account, true),
+            AccountMeta::new(*borrower_storage_account, false),
+            AccountMeta::new(*payer_account, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeBorrowerAccount {}.pack(),
     }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pay_emi_for_loan(
+    program_id: &Pubkey,
+    borrower_main_account: &Pubkey,
+    borrower_loan_mint_account_to_debit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    borrower_storage_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    emi_amount_to_pay_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*borrower_main_account, true),
+            AccountMeta::new(*borrower_loan_mint_account_to_debit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new(*borrower_storage_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::PayEMIforLoan {
+            emi_amount_to_pay_input,
+        }
+        .pack(),
+    }
+}
 
-    fn unpack_to_u64(input: &[u8]) -> Result<u64, ProgramError> {
-        msg!("in unpack");
-        let value = input
-            .get(..8)
-            .and_then(|slice| slice.try_into().ok())
-            .map(u64::from_le_bytes)
-            .ok_or(InvalidInstruction)?;
-        msg!("unpack value {}", value);
-        Ok(value)
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_loan_info_account(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    borrower_main_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    borrower_storage_account: &Pubkey,
+    guarantor_storage_account: &Pubkey,
+    loan_mint: &Pubkey,
+    payer_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    num_days_left_for_first_repayment_input: u16,
+    num_emis_needed_to_repay_the_loan_input: u16,
+    num_days_for_fundraising_input: u16,
+    total_loan_amount_input: u64,
+    annual_interest_rate_bps_input: u16,
+    amortization_type_input: u8,
+    repayment_interval_type_input: u8,
+    custom_repayment_interval_days_input: u16,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*guarantor_main_account, true),
+            AccountMeta::new_readonly(*borrower_main_account, false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*borrower_storage_account, false),
+            AccountMeta::new_readonly(*guarantor_storage_account, false),
+            AccountMeta::new_readonly(*loan_mint, false),
+            AccountMeta::new(*payer_account, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeLoanInfoAccount {
+            num_days_left_for_first_repayment_input,
+            num_emis_needed_to_repay_the_loan_input,
+            num_days_for_fundraising_input,
+            total_loan_amount_input,
+            annual_interest_rate_bps_input,
+            amortization_type_input,
+            repayment_interval_type_input,
+            custom_repayment_interval_days_input,
+        }
+        .pack(),
     }
+}
 
-    fn unpack_to_u16(input: &[u8]) -> u16 {
-        (input[0] as u16) | (input[1] as u16) << 8
+pub fn airdrop_users_with_dassi_test_coins(
+    program_id: &Pubkey,
+    airdrop_user_main_account: &Pubkey,
+    airdrop_user_storage_account: &Pubkey,
+    airdrop_user_dassi_coin_account_to_credit: &Pubkey,
+    airdrop_vault_dassi_coin_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*airdrop_user_main_account, true),
+            AccountMeta::new(*airdrop_user_storage_account, false),
+            AccountMeta::new(*airdrop_user_dassi_coin_account_to_credit, false),
+            AccountMeta::new(*airdrop_vault_dassi_coin_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::AirdropUsersWithDassiTestCoins {}.pack(),
     }
+}
 
-    pub fn unpack_to_u32(input: &[u8]) -> u32 {
-        let amount = (input[0] as u32)
-            | (input[1] as u32) << 8
-            | (input[2] as u32) << 16
-            | (input[3] as u32) << 24;
-        msg!("u32 unpack amount {}", amount);
-        return amount;
+pub fn transfer_airdrop_vault_account_ownership(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    airdrop_vault_dassi_coin_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new(*airdrop_vault_dassi_coin_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::TransferAirdropVaultAccountOwnership {}.pack(),
     }
 }
+
+pub fn return_funds_to_lenders(
+    program_id: &Pubkey,
+    fee_payer_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    loan_mint: &Pubkey,
+    lender_main_accounts: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*fee_payer_account, true),
+        AccountMeta::new(*loan_info_storage_account, false),
+    ];
+    accounts.extend(lender_accounts(
+        program_id,
+        loan_info_storage_account,
+        loan_mint,
+        lender_main_accounts,
+    ));
+    accounts.push(AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: DassiInstruction::ReturnFundsToLenders {
+            num_accounts_input: lender_main_accounts.len() as u16,
+        }
+        .pack(),
+    }
+}
+
+pub fn close_loan_info_account(
+    program_id: &Pubkey,
+    borrower_main_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    borrower_storage_account: &Pubkey,
+    rent_recipient_account: &Pubkey,
+    guarantor_stake_account: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*borrower_main_account, true),
+        AccountMeta::new(*loan_info_storage_account, false),
+        AccountMeta::new(*borrower_storage_account, false),
+        AccountMeta::new(*rent_recipient_account, false),
+    ];
+    if let Some(guarantor_stake_account) = guarantor_stake_account {
+        accounts.push(AccountMeta::new(*guarantor_stake_account, false));
+    }
+    accounts.push(AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: DassiInstruction::CloseLoanInfoAccount {}.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mark_loan_defaulted(
+    program_id: &Pubkey,
+    fee_payer_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    borrower_storage_account: &Pubkey,
+    guarantor_storage_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    guarantor_stake_vault_account: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*fee_payer_account, true),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*borrower_storage_account, false),
+            AccountMeta::new(*guarantor_storage_account, false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new(*guarantor_stake_vault_account, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::MarkLoanDefaulted {}.pack(),
+    }
+}
+
+pub fn pay_defaulted_loan_as_guarantor(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    guarantor_loan_mint_account_to_debit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    amount_to_pay_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*guarantor_main_account, true),
+            AccountMeta::new(*guarantor_loan_mint_account_to_debit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::PayDefaultedLoanAsGuarantor {
+            amount_to_pay_input,
+        }
+        .pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_guarantor_stake_vault(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    guarantor_storage_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    guarantor_stake_vault_account: &Pubkey,
+    mint_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*guarantor_main_account, true),
+            AccountMeta::new_readonly(*guarantor_storage_account, false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new(*guarantor_stake_vault_account, false),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeGuarantorStakeVault {}.pack(),
+    }
+}
+
+pub fn stake_guarantor_collateral(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    guarantor_token_account_to_debit: &Pubkey,
+    guarantor_stake_vault_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    mint_account: &Pubkey,
+    amount_to_stake_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*guarantor_main_account, true),
+            AccountMeta::new(*guarantor_token_account_to_debit, false),
+            AccountMeta::new(*guarantor_stake_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::StakeGuarantorCollateral {
+            amount_to_stake_input,
+        }
+        .pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn unstake_guarantor_collateral(
+    program_id: &Pubkey,
+    guarantor_main_account: &Pubkey,
+    guarantor_token_account_to_credit: &Pubkey,
+    guarantor_stake_vault_account: &Pubkey,
+    guarantor_stake_account: &Pubkey,
+    pda_account: &Pubkey,
+    mint_account: &Pubkey,
+    amount_to_unstake_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*guarantor_main_account, true),
+            AccountMeta::new(*guarantor_token_account_to_credit, false),
+            AccountMeta::new(*guarantor_stake_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*guarantor_stake_account, false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::UnstakeGuarantorCollateral {
+            amount_to_unstake_input,
+        }
+        .pack(),
+    }
+}
+
+pub fn initialize_config(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    params_input: ProtocolConfigParams,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: DassiInstruction::InitializeConfig { params_input }.pack(),
+    }
+}
+
+pub fn update_config(
+    program_id: &Pubkey,
+    risk_manager_account: &Pubkey,
+    params_input: ProtocolConfigParams,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*risk_manager_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::UpdateConfig { params_input }.pack(),
+    }
+}
+
+pub fn propose_admin(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    new_admin_input: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::ProposeAdmin { new_admin_input }.pack(),
+    }
+}
+
+pub fn accept_admin(program_id: &Pubkey, pending_admin_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*pending_admin_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::AcceptAdmin {}.pack(),
+    }
+}
+
+pub fn set_roles(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    pauser_input: Pubkey,
+    risk_manager_input: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::SetRoles {
+            pauser_input,
+            risk_manager_input,
+        }
+        .pack(),
+    }
+}
+
+pub fn pause_operations(
+    program_id: &Pubkey,
+    pauser_account: &Pubkey,
+    operations_input: u8,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*pauser_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::PauseOperations { operations_input }.pack(),
+    }
+}
+
+pub fn unpause_operations(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    operations_input: u8,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::UnpauseOperations { operations_input }.pack(),
+    }
+}
+
+pub fn initialize_lender_account(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    mint_account: &Pubkey,
+    payer_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*lender_main_account, true),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new(*payer_account, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeLenderAccount {}.pack(),
+    }
+}
+
+pub fn migrate_lender_account(
+    program_id: &Pubkey,
+    payer_account: &Pubkey,
+    lenders_data_storage_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    lender_id_input: u32,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer_account, true),
+            AccountMeta::new(*lenders_data_storage_account, false),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::MigrateLenderAccount { lender_id_input }.pack(),
+    }
+}
+
+pub fn credit_repayments_to_lenders(
+    program_id: &Pubkey,
+    fee_payer_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    loan_mint: &Pubkey,
+    lender_main_accounts: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*fee_payer_account, true),
+        AccountMeta::new(*loan_info_storage_account, false),
+    ];
+    accounts.extend(lender_accounts(
+        program_id,
+        loan_info_storage_account,
+        loan_mint,
+        lender_main_accounts,
+    ));
+    accounts.push(AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: DassiInstruction::CreditRepaymentsToLenders {
+            num_accounts_input: lender_main_accounts.len() as u16,
+        }
+        .pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_lending_commitment(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    lender_loan_mint_account_to_credit: &Pubkey,
+    loan_mint_vault_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    lender_position_account: &Pubkey,
+    pda_account: &Pubkey,
+    lender_index_input: u8,
+    amount_to_cancel_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*lender_main_account, true),
+            AccountMeta::new(*lender_loan_mint_account_to_credit, false),
+            AccountMeta::new(*loan_mint_vault_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new(*lender_position_account, false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::CancelLendingCommitment {
+            lender_index_input,
+            amount_to_cancel_input,
+        }
+        .pack(),
+    }
+}
+
+pub fn relend_from_free_balance(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    lender_position_account: &Pubkey,
+    amount_to_lend_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lender_main_account, true),
+            AccountMeta::new(*loan_info_storage_account, false),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new(*lender_position_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::RelendFromFreeBalance {
+            amount_to_lend_input,
+        }
+        .pack(),
+    }
+}
+
+pub fn set_lending_strategy(
+    program_id: &Pubkey,
+    lender_main_account: &Pubkey,
+    lender_storage_account: &Pubkey,
+    max_amount_per_loan_input: u64,
+    min_credit_score_input: u64,
+    min_approval_score_input: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*lender_main_account, true),
+            AccountMeta::new(*lender_storage_account, false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::SetLendingStrategy {
+            max_amount_per_loan_input,
+            min_credit_score_input,
+            min_approval_score_input,
+        }
+        .pack(),
+    }
+}
+
+// loans are (loan info storage account, borrower storage account, guarantor storage account) of every loan to lend to, all
+// of them in mint
+pub fn allocate_free_balance_to_loans(
+    program_id: &Pubkey,
+    fee_payer_account: &Pubkey,
+    lender_main_account: &Pubkey,
+    mint: &Pubkey,
+    loans: &[(Pubkey, Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*fee_payer_account, true),
+        AccountMeta::new(get_lender_storage_address(lender_main_account, mint, program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    for (loan_info_storage_account, borrower_storage_account, guarantor_storage_account) in loans {
+        accounts.push(AccountMeta::new(*loan_info_storage_account, false));
+        accounts.push(AccountMeta::new_readonly(*borrower_storage_account, false));
+        accounts.push(AccountMeta::new_readonly(*guarantor_storage_account, false));
+        accounts.push(AccountMeta::new(
+            get_lender_position_address(lender_main_account, loan_info_storage_account, program_id).0,
+            false,
+        ));
+    }
+    accounts.push(AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: DassiInstruction::AllocateFreeBalanceToLoans {
+            num_loans_input: loans.len() as u8,
+        }
+        .pack(),
+    }
+}
+
+pub fn initialize_mint_vault(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    mint_account: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin_account, true),
+            AccountMeta::new(get_mint_vault_address(mint_account, program_id).0, false),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::InitializeMintVault {}.pack(),
+    }
+}
+
+pub fn add_accepted_loan_mint(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    mint_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new_readonly(*mint_account, false),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::AddAcceptedLoanMint {}.pack(),
+    }
+}
+
+pub fn migrate_legacy_vault(
+    program_id: &Pubkey,
+    admin_account: &Pubkey,
+    legacy_vault_account: &Pubkey,
+    dassi_coin_mint: &Pubkey,
+    pda_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin_account, true),
+            AccountMeta::new(*legacy_vault_account, false),
+            AccountMeta::new(get_mint_vault_address(dassi_coin_mint, program_id).0, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*pda_account, false),
+            AccountMeta::new(get_protocol_config_address(program_id).0, false),
+        ],
+        data: DassiInstruction::MigrateLegacyVault {}.pack(),
+    }
+}
+
+// lender storage account and lender position account of every lender, as ReturnFundsToLenders and CreditRepaymentsToLenders
+// expect them
+fn lender_accounts(
+    program_id: &Pubkey,
+    loan_info_storage_account: &Pubkey,
+    loan_mint: &Pubkey,
+    lender_main_accounts: &[Pubkey],
+) -> Vec<AccountMeta> {
+    lender_main_accounts
+        .iter()
+        .flat_map(|lender_main_account| {
+            vec![
+                AccountMeta::new(
+                    get_lender_storage_address(lender_main_account, loan_mint, program_id).0,
+                    false,
+                ),
+                AccountMeta::new(
+                    get_lender_position_address(
+                        lender_main_account,
+                        loan_info_storage_account,
+                        program_id,
+                    )
+                    .0,
+                    false,
+                ),
+            ]
+        })
+        .collect()
+}
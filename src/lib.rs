@@ -1,8 +1,11 @@
+pub mod amortization;
+pub mod credit_score;
 pub mod error;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod utils;
+pub mod validation;
 
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
@@ -1,8 +1,13 @@
 //use crate::utils::{self, AccTypes};
 use crate::{
+    amortization, amortization::AmortizationType, amortization::LoanTerms,
+    amortization::RepaymentIntervalType, credit_score,
     error::DassiError, instruction::DassiInstruction, state, state::AccTypes,
-    state::BorrowerAccount, state::GuarantorAccount, state::LenderAccountData,
+    state::BorrowerAccount, state::GuarantorAccount, state::GuarantorStakeAccount, state::LenderAccount, state::LegacyLenderAccountData, state::LenderPositionAccount,
+    state::LenderPositionStatus,
     state::LoanInfoAccDataHeader, state::LoanInfoAccLendersData,
+    state::EmiStatus, state::LoanStatus, state::ProtocolConfig, state::ProtocolConfigParams,
+    utils, validation,
 };
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
@@ -11,8 +16,9 @@ use solana_program::{
     program::invoke,
     program::invoke_signed,
     program_error::ProgramError,
-    program_pack::{IsInitialized, Pack},
+    program_pack::Pack,
     pubkey::Pubkey,
+    system_instruction,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 //use solana_program_test::tokio::fs::remove_dir;
@@ -25,8 +31,6 @@ use std::convert::TryInto;
 //solana_sdk::declare_id!("EnvhHCLvg55P7PDtbvR1NwuTuAeodqpusV3MR5QEK8gs");
 //const ADMIN_PUBKEY: &Pubkey = Pubkey::from_str("EnvhHCLvg55P7PDtbvR1NwuTuAeodqpusV3MR5QEK8gs");
 
-const DASSI_COIN_DECIMALS: u64 = 1000_000_000;
-const MIN_LENDING_AMOUNT: u64 = 10_000_000_000u64;
 pub struct Processor;
 impl Processor {
     pub fn process(
@@ -37,26 +41,68 @@ impl Processor {
         msg!("in processor");
         let instruction = DassiInstruction::unpack(instruction_data)?;
         msg!("out instruction");
+
+        // every instruction except InitializeConfig takes protocol config account as its last account
+        let config = match instruction {
+            DassiInstruction::InitializeConfig { params_input } => {
+                msg!("DassiInstruction::InitializeConfig");
+                return Self::process_initialize_config(accounts, params_input, program_id);
+            }
+            _ => Self::load_protocol_config(accounts, program_id)?,
+        };
+
+        if let Some(operation) = Self::get_pausable_operation(&instruction) {
+            if config.is_paused(operation) {
+                msg!("operation is paused");
+                return Err(DassiError::OperationPaused.into());
+            }
+        }
+
         match instruction {
+            DassiInstruction::InitializeConfig { .. } => Err(DassiError::InvalidInstruction.into()),
+
+            DassiInstruction::UpdateConfig { params_input } => {
+                msg!("DassiInstruction::UpdateConfig");
+                Self::process_update_config(accounts, params_input, config, program_id)
+            }
+
+            DassiInstruction::ProposeAdmin { new_admin_input } => {
+                msg!("DassiInstruction::ProposeAdmin");
+                Self::process_propose_admin(accounts, new_admin_input, config, program_id)
+            }
+
+            DassiInstruction::AcceptAdmin {} => {
+                msg!("DassiInstruction::AcceptAdmin");
+                Self::process_accept_admin(accounts, config, program_id)
+            }
+
+            DassiInstruction::SetRoles {
+                pauser_input,
+                risk_manager_input,
+            } => {
+                msg!("DassiInstruction::SetRoles");
+                Self::process_set_roles(accounts, pauser_input, risk_manager_input, config, program_id)
+            }
+
+            DassiInstruction::PauseOperations { operations_input } => {
+                msg!("DassiInstruction::PauseOperations");
+                Self::process_pause_operations(accounts, operations_input, config, program_id)
+            }
+
+            DassiInstruction::UnpauseOperations { operations_input } => {
+                msg!("DassiInstruction::UnpauseOperations");
+                Self::process_unpause_operations(accounts, operations_input, config, program_id)
+            }
+
             DassiInstruction::LendToBorrower {
                 amount_to_lend_input,
-                lender_id_input,
             } => {
                 msg!("DassiInstruction::LendToBorrower");
-                Self::process_lend_to_borrower(
-                    accounts,
-                    amount_to_lend_input,
-                    lender_id_input,
-                    program_id,
-                )
+                Self::process_lend_to_borrower(accounts, amount_to_lend_input, &config, program_id)
             }
-            DassiInstruction::WithdrawLenderFreeWalletFunds { lender_id_input } => {
+            DassiInstruction::WithdrawLenderFreeWalletFunds {} => {
                 msg!("DassiInstruction::WithdrawLenderFreeWalletFunds");
-                Self::process_withdraw_lender_free_wallet_funds(
-                    accounts,
-                    lender_id_input,
-                    program_id,
-                )
+                Self::process_withdraw_lender_free_wallet_funds(accounts, program_id)
             }
             DassiInstruction::WithdrawCollectedLoanFunds {} => {
                 msg!("DassiInstruction::WithdrawCollectedLoanFunds");
@@ -65,27 +111,29 @@ impl Processor {
 
             DassiInstruction::TransferDassiVaultAccountOwnership {} => {
                 msg!("DassiInstruction::TransferDassiVaultAccountOwnership");
-                Self::process_transfer_dassi_vault_account_ownership(accounts, program_id)
+                msg!("vault is created at its pda by InitializeMintVault");
+                Err(DassiError::InvalidInstruction.into())
             }
 
             DassiInstruction::InitializeLendersStorageAccount {} => {
                 msg!("DassiInstruction::InitializeLendersStorageAccount");
-                Self::process_initialize_lenders_storage_account(accounts)
+                msg!("lenders storage account is deprecated, use InitializeLenderAccount");
+                Err(DassiError::InvalidInstruction.into())
             }
             DassiInstruction::InitializeBorrowerAccount {} => {
                 msg!("DassiInstruction::InitializeBorrowerAccount");
-                Self::process_initialize_borrower_storage_account(accounts, program_id)
+                Self::process_initialize_borrower_storage_account(accounts, &config, program_id)
             }
             DassiInstruction::InitializeGuarantorAccount {} => {
                 msg!("DassiInstruction::InitializeGuarantorAccount");
-                Self::process_initialize_guarantor_storage_account(accounts, program_id)
+                Self::process_initialize_guarantor_storage_account(accounts, &config, program_id)
             }
 
             DassiInstruction::PayEMIforLoan {
                 emi_amount_to_pay_input,
             } => {
                 msg!("DassiInstruction::PayEMIforLoan");
-                Self::process_pay_emi(accounts, emi_amount_to_pay_input, program_id)
+                Self::process_pay_emi(accounts, emi_amount_to_pay_input, &config, program_id)
             }
 
             DassiInstruction::InitializeLoanInfoAccount {
@@ -93,6 +141,10 @@ impl Processor {
                 num_emis_needed_to_repay_the_loan_input,
                 num_days_for_fundraising_input,
                 total_loan_amount_input,
+                annual_interest_rate_bps_input,
+                amortization_type_input,
+                repayment_interval_type_input,
+                custom_repayment_interval_days_input,
             } => {
                 msg!("DassiInstruction::InitializeLoanInfoAccount");
                 Self::initialize_loan_info_account(
@@ -101,18 +153,23 @@ impl Processor {
                     num_emis_needed_to_repay_the_loan_input,
                     num_days_for_fundraising_input,
                     total_loan_amount_input,
+                    annual_interest_rate_bps_input,
+                    amortization_type_input,
+                    repayment_interval_type_input,
+                    custom_repayment_interval_days_input,
+                    &config,
                     program_id,
                 )
             }
 
             DassiInstruction::AirdropUsersWithDassiTestCoins {} => {
                 msg!("DassiInstruction::AirdropUsersWithDassiTestCoins");
-                Self::process_airdrop_users_with_dassi_test_coins(accounts, program_id)
+                Self::process_airdrop_users_with_dassi_test_coins(accounts, &config, program_id)
             }
 
             DassiInstruction::TransferAirdropVaultAccountOwnership {} => {
                 msg!("DassiInstruction::TransferAirdropVaultAccountOwnership");
-                Self::process_transfer_airdrop_vault_account_ownership(accounts, program_id)
+                Self::process_transfer_airdrop_vault_account_ownership(accounts, &config, program_id)
             }
 
             DassiInstruction::ReturnFundsToLenders { num_accounts_input } => {
@@ -122,7 +179,114 @@ impl Processor {
 
             DassiInstruction::CloseLoanInfoAccount {} => {
                 msg!("DassiInstruction::CloseLoanInfoAccount");
-                Self::process_close_loan_info_account(accounts)
+                Self::process_close_loan_info_account(accounts, program_id)
+            }
+
+            DassiInstruction::MarkLoanDefaulted {} => {
+                msg!("DassiInstruction::MarkLoanDefaulted");
+                Self::process_mark_loan_defaulted(accounts, &config, program_id)
+            }
+
+            DassiInstruction::PayDefaultedLoanAsGuarantor {
+                amount_to_pay_input,
+            } => {
+                msg!("DassiInstruction::PayDefaultedLoanAsGuarantor");
+                Self::process_pay_defaulted_loan_as_guarantor(accounts, amount_to_pay_input, program_id)
+            }
+
+            DassiInstruction::InitializeGuarantorStakeVault {} => {
+                msg!("DassiInstruction::InitializeGuarantorStakeVault");
+                Self::process_initialize_guarantor_stake_vault(accounts, &config, program_id)
+            }
+
+            DassiInstruction::StakeGuarantorCollateral {
+                amount_to_stake_input,
+            } => {
+                msg!("DassiInstruction::StakeGuarantorCollateral");
+                Self::process_stake_guarantor_collateral(accounts, amount_to_stake_input, &config, program_id)
+            }
+
+            DassiInstruction::UnstakeGuarantorCollateral {
+                amount_to_unstake_input,
+            } => {
+                msg!("DassiInstruction::UnstakeGuarantorCollateral");
+                Self::process_unstake_guarantor_collateral(
+                    accounts,
+                    amount_to_unstake_input,
+                    &config,
+                    program_id,
+                )
+            }
+
+            DassiInstruction::InitializeLenderAccount {} => {
+                msg!("DassiInstruction::InitializeLenderAccount");
+                Self::process_initialize_lender_storage_account(accounts, &config, program_id)
+            }
+
+            DassiInstruction::MigrateLenderAccount { lender_id_input } => {
+                msg!("DassiInstruction::MigrateLenderAccount");
+                Self::process_migrate_lender_account(accounts, lender_id_input, &config, program_id)
+            }
+
+            DassiInstruction::CreditRepaymentsToLenders { num_accounts_input } => {
+                msg!("DassiInstruction::CreditRepaymentsToLenders");
+                Self::process_credit_repayments_to_lenders(accounts, num_accounts_input, program_id)
+            }
+
+            DassiInstruction::CancelLendingCommitment {
+                lender_index_input,
+                amount_to_cancel_input,
+            } => {
+                msg!("DassiInstruction::CancelLendingCommitment");
+                Self::process_cancel_lending_commitment(
+                    accounts,
+                    lender_index_input,
+                    amount_to_cancel_input,
+                    &config,
+                    program_id,
+                )
+            }
+
+            DassiInstruction::RelendFromFreeBalance {
+                amount_to_lend_input,
+            } => {
+                msg!("DassiInstruction::RelendFromFreeBalance");
+                Self::process_relend_from_free_balance(accounts, amount_to_lend_input, &config, program_id)
+            }
+
+            DassiInstruction::SetLendingStrategy {
+                max_amount_per_loan_input,
+                min_credit_score_input,
+                min_approval_score_input,
+            } => {
+                msg!("DassiInstruction::SetLendingStrategy");
+                Self::process_set_lending_strategy(
+                    accounts,
+                    max_amount_per_loan_input,
+                    min_credit_score_input,
+                    min_approval_score_input,
+                    program_id,
+                )
+            }
+
+            DassiInstruction::AllocateFreeBalanceToLoans { num_loans_input } => {
+                msg!("DassiInstruction::AllocateFreeBalanceToLoans");
+                Self::process_allocate_free_balance_to_loans(accounts, num_loans_input, &config, program_id)
+            }
+
+            DassiInstruction::InitializeMintVault {} => {
+                msg!("DassiInstruction::InitializeMintVault");
+                Self::process_initialize_mint_vault(accounts, &config, program_id)
+            }
+
+            DassiInstruction::AddAcceptedLoanMint {} => {
+                msg!("DassiInstruction::AddAcceptedLoanMint");
+                Self::process_add_accepted_loan_mint(accounts, config, program_id)
+            }
+
+            DassiInstruction::MigrateLegacyVault {} => {
+                msg!("DassiInstruction::MigrateLegacyVault");
+                Self::process_migrate_legacy_vault(accounts, config, program_id)
             }
         }
     }
@@ -130,7 +294,7 @@ impl Processor {
     fn process_lend_to_borrower(
         accounts: &[AccountInfo],
         amount_to_lend_input: u64,
-        lender_id_input: u32,
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -141,28 +305,35 @@ impl Processor {
         }
 
         let lender_dassi_coin_account_to_debit = next_account_info(account_info_iter)?;
-
         // dassi_coin_vault_account is program controlled vault account and can be only controlled by our deployed program for debit funds or any kind of operation
         let dassi_coin_vault_account = next_account_info(account_info_iter)?;
 
-        
-
         let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
 
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
         }
 
+        let loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_storage_account.data.borrow()
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )?;
+        let loan_mint = loan_info_header_data.loan_mint;
+
+        validation::validate_user_token_account(
+            lender_dassi_coin_account_to_debit,
+            lender_main_account.key,
+            &loan_mint,
+        )?;
         let dassi_coin_vault_account_data_before =
-            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
+            validation::validate_mint_vault(dassi_coin_vault_account, &loan_mint, program_id)?;
         let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
 
-        let (pda_dassi_vault, _bump_seed) =
-            Pubkey::find_program_address(&[b"DassiFinance"], program_id);
-
-        if dassi_coin_vault_account_data_before.owner != pda_dassi_vault {
-            return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
-        }
+        let amount_to_transfer =
+            Self::get_amount_to_lend(&loan_info_header_data, amount_to_lend_input, config)?;
 
         let transfer_lending_amount_to_vault_ix = spl_token::instruction::transfer(
             token_program.key,
@@ -170,13 +341,13 @@ impl Processor {
             dassi_coin_vault_account.key,
             lender_main_account.key,
             &[],
-            amount_to_lend_input,
+            amount_to_transfer,
         )?;
 
         msg!("Calling the token program to transfer lending amount to vault...");
         msg!(
-            "amount of dassi coin tokens to transfer {}, lender debit key {}",
-            (amount_to_lend_input as f64 / DASSI_COIN_DECIMALS as f64),
+            "amount of tokens to transfer {}, lender debit key {}",
+            spl_token::amount_to_ui_amount(amount_to_transfer, loan_info_header_data.loan_mint_decimals),
             lender_dassi_coin_account_to_debit.key.to_string()
         );
 
@@ -201,225 +372,414 @@ impl Processor {
             .checked_sub(dassi_coin_vault_balance_before)
             .unwrap();
     
-        // Minimum DassiCoin to lend = 10
-        if vault_balance_increased < MIN_LENDING_AMOUNT {
+        if vault_balance_increased != amount_to_transfer {
             return Err(DassiError::ExpectedAmountMismatch.into());
         }
-       
-        let loan_info_storage_account = next_account_info(account_info_iter)?;
-       
-        if loan_info_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-       
-        let lenders_data_storage_account = next_account_info(account_info_iter)?;
-    
-        if lenders_data_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-       
-        let mut lenders_storage_data_byte_array =
-            lenders_data_storage_account.try_borrow_mut_data()?;
-        
-        if lenders_storage_data_byte_array[0] != AccTypes::LendersAcc as u8 {
-            return Err(DassiError::ExpectedAccountTypeMismatched.into());
-        }
-      
-        if lenders_storage_data_byte_array[1] != 1u8 {
-            return Err(DassiError::ExpectedLendersAccNumNotMatched.into());
-        }   
-     
-        if lender_id_input > 49_999u32 {
-            return Err(DassiError::InvalidLenderIdInput.into());
-        }
-      
-        // lender_id_input can vary from 0 to 49_999 included
-        let lender_si_in_lenders_data_byte_array: usize = 2usize
-            + (lender_id_input as usize)
-                .checked_mul(state::LENDER_ACC_DATA_SIZE)
-                .unwrap();
-        let lender_ei_in_lenders_data_byte_array: usize =
-            lender_si_in_lenders_data_byte_array + state::LENDER_ACC_DATA_SIZE;
-        let mut lender_acc_data: LenderAccountData = state::unpack_to_lender_account_data(
-            &lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
-        )
-        .unwrap();
 
-        //let lender_main_account_si: usize = lender_si_in_lenders_data_byte_array + 1usize;
-        //let lender_main_account_ei: usize = lender_main_account_si + 32usize;
-        // means lender is lending for first time, initialize his account
-      
-        if lender_acc_data.is_account_active != 1u8 {
-            lender_acc_data.is_account_active = 1u8;
-            lender_acc_data.lender_main_acc_pubkey = *lender_main_account.key;
-        } else {
-         
-            if lender_acc_data.lender_main_acc_pubkey != *lender_main_account.key {
-                return Err(DassiError::InvalidLenderIdInput.into());
-            }
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, lender_main_account.key, program_id)?;
+        if lender_data.mint != loan_mint {
+            return Err(DassiError::MintDoesNotMatched.into());
         }
-       
-        // update lender data in LendersAccountDataArray
-        lender_acc_data.total_lending_amount = lender_acc_data
+
+        lender_data.total_lending_amount = lender_data
             .total_lending_amount
             .checked_add(vault_balance_increased as u128)
             .unwrap();
-        lender_acc_data.total_unique_lending_amount = lender_acc_data
+        lender_data.total_unique_lending_amount = lender_data
             .total_unique_lending_amount
             .checked_add(vault_balance_increased)
             .unwrap();
-         
-        state::pack_to_lender_account_data(
-            lender_acc_data,
-            &mut lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
-        )
-        .unwrap();
 
-        // update lender payment in LoanInfoAccData
-        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
-        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
-            &loan_info_data_byte_array
-                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        let lender_position_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        Self::record_lending(
+            lender_main_account.key,
+            loan_info_storage_account,
+            lender_position_account,
+            lender_main_account,
+            system_program,
+            vault_balance_increased,
+            program_id,
         )
-        .unwrap();
+    }
 
-        if loan_info_header_data.acc_type != AccTypes::LoanInfoAcc as u8 {
-            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+    // Free balance is already in DassiCoin vault, so lending from it only moves it from lender account to loan
+    fn process_relend_from_free_balance(
+        accounts: &[AccountInfo],
+        amount_to_lend_input: u64,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let lender_main_account = next_account_info(account_info_iter)?;
+
+        if !lender_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let now = Clock::get()?.unix_timestamp as u64;
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
 
-        if loan_info_header_data.fundraising_period_ending_timestamp < now
-            && loan_info_header_data.total_amount_lended < loan_info_header_data.total_loan_amount
-        {
-            // fundraising period expired, and loan is not fulfilled so, send funds back to lenders
-            return Err(DassiError::FundraisingPeriodExpired.into());
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
         }
-     
-        if loan_info_header_data.total_amount_lended >= loan_info_header_data.total_loan_amount {
-            return Err(DassiError::BorrowerAlreadyFunded.into());
+
+        let loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_storage_account.data.borrow()
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )?;
+        let amount_to_lend =
+            Self::get_amount_to_lend(&loan_info_header_data, amount_to_lend_input, config)?;
+
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, lender_main_account.key, program_id)?;
+        if lender_data.mint != loan_info_header_data.loan_mint {
+            return Err(DassiError::MintDoesNotMatched.into());
         }
 
-        let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
-            + (loan_info_header_data.next_index_to_store_lender_data as usize)
-                * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
-        let loan_info_lender_data_ei: usize =
-            loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
-        let mut loan_info_lender_data: LoanInfoAccLendersData =
-            state::unpack_to_loan_info_acc_lender_data(
-                &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
-            )
+        if lender_data.amount_to_withdraw_or_lend < amount_to_lend {
+            return Err(DassiError::InsufficientFreeBalance.into());
+        }
+
+        // money stays in protocol, so total_unique_lending_amount doesn't change
+        lender_data.amount_to_withdraw_or_lend = lender_data
+            .amount_to_withdraw_or_lend
+            .checked_sub(amount_to_lend)
+            .unwrap();
+        lender_data.total_lending_amount = lender_data
+            .total_lending_amount
+            .checked_add(amount_to_lend as u128)
             .unwrap();
 
-        loan_info_lender_data.lender_main_acc_pubkey = *lender_main_account.key;
-        loan_info_lender_data.lenders_data_storage_acc_number = 1u8;
-        loan_info_lender_data.lender_id = lender_id_input;
-        loan_info_lender_data.lent_amount = vault_balance_increased;
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
 
-        state::pack_to_loan_info_acc_lender_data(
-            loan_info_lender_data,
-            &mut loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
-        )
-        .unwrap();
+        msg!(
+            "lending {} tokens from free balance",
+            spl_token::amount_to_ui_amount(amount_to_lend, loan_info_header_data.loan_mint_decimals)
+        );
 
-        loan_info_header_data.next_index_to_store_lender_data =
-            loan_info_header_data.next_index_to_store_lender_data + 1;
-        loan_info_header_data.total_amount_lended = loan_info_header_data
-            .total_amount_lended
-            .checked_add(vault_balance_increased)
-            .unwrap();
-        state::pack_to_loan_info_header(
-            loan_info_header_data,
-            &mut loan_info_data_byte_array
-                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
-        )
-        .unwrap();
+        let lender_position_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
 
-        Ok(())
+        Self::record_lending(
+            lender_main_account.key,
+            loan_info_storage_account,
+            lender_position_account,
+            lender_main_account,
+            system_program,
+            amount_to_lend,
+            program_id,
+        )
     }
 
-    fn process_pay_emi(
+    fn process_set_lending_strategy(
         accounts: &[AccountInfo],
-        emi_amount_to_pay_input: u64,
-
+        max_amount_per_loan_input: u64,
+        min_credit_score_input: u64,
+        min_approval_score_input: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let borrower_main_account = next_account_info(account_info_iter)?;
+        let lender_main_account = next_account_info(account_info_iter)?;
 
-        if !borrower_main_account.is_signer {
+        if !lender_main_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let borrower_dassi_coin_account_to_debit = next_account_info(account_info_iter)?;
-
-        // dassi_coin_vault_account is program controlled vault account and can be only controlled by our deployed program for debit funds or any kind of operation
-        let dassi_coin_vault_account = next_account_info(account_info_iter)?;
-        
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, lender_main_account.key, program_id)?;
 
-
-        let borrower_storage_account = next_account_info(account_info_iter)?;
-        if borrower_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
+        // credit score and approval score have same scale
+        if min_credit_score_input > credit_score::MAX_CREDIT_SCORE
+            || min_approval_score_input > credit_score::MAX_CREDIT_SCORE
+        {
+            return Err(DassiError::InvalidLendingStrategy.into());
         }
 
-        /*
-        let expected_borrower_storage_account_pubkey = Pubkey::create_with_seed(
-            borrower_main_account.key,
-            "DassiFinanceBorrower",
-            program_id,
+        lender_data.strategy_max_amount_per_loan = max_amount_per_loan_input;
+        lender_data.strategy_min_credit_score = min_credit_score_input;
+        lender_data.strategy_min_approval_score = min_approval_score_input;
+
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
         )?;
 
-        if expected_borrower_storage_account_pubkey != *borrower_storage_account.key {
-            return Err(DassiError::AccountMismatched.into());
+        Ok(())
+    }
+
+    // Lends free balance of a lender to loans matching his lending strategy, like RelendFromFreeBalance does. Lender opted in by
+    // setting the strategy, so it doesn't need his signature. Loan which is not raising funds anymore, doesn't match the strategy
+    // or can't take any more of lender's funds is skipped, so one stale loan in the list doesn't fail the whole call
+    fn process_allocate_free_balance_to_loans(
+        accounts: &[AccountInfo],
+        num_loans_input: u8,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let fee_payer_account = next_account_info(account_info_iter)?;
+
+        if !fee_payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-        */
 
-        let token_program = next_account_info(account_info_iter)?;
+        if num_loans_input == 0 {
+            return Err(DassiError::InvalidInstruction.into());
+        }
 
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
+        let lender_storage_account = next_account_info(account_info_iter)?;
+
+        if lender_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
         }
 
-        let dassi_coin_vault_account_data_before =
-            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
-            let (pda_dassi_vault, _bump_seed) =
-            Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        let lender_main_pubkey =
+            LenderAccount::unpack(&lender_storage_account.data.try_borrow().unwrap())?
+                .lender_main_acc_pubkey;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, &lender_main_pubkey, program_id)?;
 
-        if dassi_coin_vault_account_data_before.owner != pda_dassi_vault {
-            return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+        if lender_data.strategy_max_amount_per_loan == 0 {
+            return Err(DassiError::LendingStrategyNotEnabled.into());
         }
-        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
-        let transfer_emi_amount_to_vault_ix = spl_token::instruction::transfer(
-            token_program.key,
-            borrower_dassi_coin_account_to_debit.key,
-            dassi_coin_vault_account.key,
-            borrower_main_account.key,
-            &[],
-            emi_amount_to_pay_input,
-        )?;
 
-        msg!("Calling the token program to transfer emi amount to vault...");
-        msg!(
-            "amount of dassi coin tokens to transfer {}, borrower debit key {}",
-            (emi_amount_to_pay_input as f64 / DASSI_COIN_DECIMALS as f64),
-            borrower_dassi_coin_account_to_debit.key.to_string()
-        );
+        let system_program = next_account_info(account_info_iter)?;
+        let now = Clock::get()?.unix_timestamp as u64;
 
-        invoke(
-            &transfer_emi_amount_to_vault_ix,
-            &[
-                borrower_dassi_coin_account_to_debit.clone(),
-                dassi_coin_vault_account.clone(),
-                borrower_main_account.clone(),
-                token_program.clone(),
-            ],
-        )?;
+        for _ in 0..num_loans_input {
+            let loan_info_storage_account = next_account_info(account_info_iter)?;
+            let borrower_storage_account = next_account_info(account_info_iter)?;
+            let guarantor_storage_account = next_account_info(account_info_iter)?;
+            let lender_position_account = next_account_info(account_info_iter)?;
 
-        let dassi_coin_vault_account_data_after =
-            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
+            if loan_info_storage_account.owner != program_id {
+                return Err(DassiError::WrongAccountPassed.into());
+            }
+
+            let loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+                &loan_info_storage_account.data.borrow()
+                    [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+            )?;
+            validation::check_loan_info_account(
+                loan_info_storage_account,
+                &loan_info_header_data,
+                program_id,
+            )?;
+            validation::check_borrower_storage_account(
+                borrower_storage_account,
+                &loan_info_header_data.borrower_main_acc_pubkey,
+                program_id,
+            )?;
+            validation::check_guarantor_storage_account(
+                guarantor_storage_account,
+                &loan_info_header_data.guarantor_main_acc_pubkey,
+                program_id,
+            )?;
+
+            // free balance can only be lent to loans in its own mint
+            if loan_info_header_data.loan_mint != lender_data.mint {
+                return Err(DassiError::MintDoesNotMatched.into());
+            }
+
+            let remaining_loan_amount = loan_info_header_data
+                .total_loan_amount
+                .saturating_sub(loan_info_header_data.total_amount_lended);
+            if Self::get_loan_status(&loan_info_header_data)? != LoanStatus::Fundraising
+                || loan_info_header_data.fundraising_period_ending_timestamp < now
+                || remaining_loan_amount == 0
+                || loan_info_header_data.next_index_to_store_lender_data
+                    >= state::MAX_NUMBER_OF_LENDERS_PER_LOAN
+            {
+                msg!(
+                    "skipping loan {}, it is not raising funds",
+                    loan_info_storage_account.key.to_string()
+                );
+                continue;
+            }
+
+            let borrower_data =
+                BorrowerAccount::unpack(&borrower_storage_account.data.try_borrow().unwrap())?;
+            let guarantor_data =
+                GuarantorAccount::unpack(&guarantor_storage_account.data.try_borrow().unwrap())?;
+
+            if borrower_data.credit_score < lender_data.strategy_min_credit_score
+                || guarantor_data.approval_score < lender_data.strategy_min_approval_score
+            {
+                msg!(
+                    "skipping loan {}, it doesn't match lending strategy",
+                    loan_info_storage_account.key.to_string()
+                );
+                continue;
+            }
+
+            // amount lender already has in this loan counts towards max amount per loan
+            let already_lent_amount = if lender_position_account.data_len() == 0 {
+                0u64
+            } else {
+                Self::load_lender_position_account(
+                    lender_position_account,
+                    &lender_main_pubkey,
+                    loan_info_storage_account.key,
+                    program_id,
+                )?
+                .principal_lent
+            };
+            let amount_available = lender_data
+                .strategy_max_amount_per_loan
+                .saturating_sub(already_lent_amount)
+                .min(lender_data.amount_to_withdraw_or_lend);
+            let lending_amount_unit =
+                Self::get_lending_amount_unit(loan_info_header_data.loan_mint_decimals)?;
+            let amount_to_lend_input = amount_available
+                .checked_div(lending_amount_unit)
+                .unwrap()
+                .checked_mul(lending_amount_unit)
+                .unwrap();
+
+            // only last lending of a loan can be smaller than min lending amount
+            let min_lending_amount =
+                Self::get_min_lending_amount(loan_info_header_data.loan_mint_decimals, config)?;
+            if amount_to_lend_input == 0
+                || (amount_to_lend_input < min_lending_amount
+                    && amount_to_lend_input < remaining_loan_amount)
+            {
+                msg!(
+                    "skipping loan {}, lender can't lend enough to it",
+                    loan_info_storage_account.key.to_string()
+                );
+                continue;
+            }
+
+            let amount_to_lend =
+                Self::get_amount_to_lend(&loan_info_header_data, amount_to_lend_input, config)?;
+
+            // money stays in protocol, so total_unique_lending_amount doesn't change
+            lender_data.amount_to_withdraw_or_lend = lender_data
+                .amount_to_withdraw_or_lend
+                .checked_sub(amount_to_lend)
+                .unwrap();
+            lender_data.total_lending_amount = lender_data
+                .total_lending_amount
+                .checked_add(amount_to_lend as u128)
+                .unwrap();
+
+            msg!(
+                "lending {} tokens to loan {}",
+                spl_token::amount_to_ui_amount(amount_to_lend, loan_info_header_data.loan_mint_decimals),
+                loan_info_storage_account.key.to_string()
+            );
+
+            Self::record_lending(
+                &lender_main_pubkey,
+                loan_info_storage_account,
+                lender_position_account,
+                fee_payer_account,
+                system_program,
+                amount_to_lend,
+                program_id,
+            )?;
+        }
+
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        Ok(())
+    }
+
+    fn process_pay_emi(
+        accounts: &[AccountInfo],
+        emi_amount_to_pay_input: u64,
+
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let borrower_main_account = next_account_info(account_info_iter)?;
+
+        if !borrower_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let borrower_dassi_coin_account_to_debit = next_account_info(account_info_iter)?;
+        // dassi_coin_vault_account is program controlled vault account and can be only controlled by our deployed program for debit funds or any kind of operation
+        let dassi_coin_vault_account = next_account_info(account_info_iter)?;
+
+        let borrower_storage_account = next_account_info(account_info_iter)?;
+        validation::check_borrower_storage_account(
+            borrower_storage_account,
+            borrower_main_account.key,
+            program_id,
+        )?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        // emi is paid in mint of the loan
+        let (loan_mint, loan_mint_decimals) = {
+            let loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+                &loan_info_storage_account.data.borrow()
+                    [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+            )?;
+            (loan_info_header_data.loan_mint, loan_info_header_data.loan_mint_decimals)
+        };
+        validation::validate_user_token_account(
+            borrower_dassi_coin_account_to_debit,
+            borrower_main_account.key,
+            &loan_mint,
+        )?;
+        let dassi_coin_vault_account_data_before =
+            validation::validate_mint_vault(dassi_coin_vault_account, &loan_mint, program_id)?;
+
+        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
+        let transfer_emi_amount_to_vault_ix = spl_token::instruction::transfer(
+            token_program.key,
+            borrower_dassi_coin_account_to_debit.key,
+            dassi_coin_vault_account.key,
+            borrower_main_account.key,
+            &[],
+            emi_amount_to_pay_input,
+        )?;
+
+        msg!("Calling the token program to transfer emi amount to vault...");
+        msg!(
+            "amount of tokens to transfer {}, borrower debit key {}",
+            spl_token::amount_to_ui_amount(emi_amount_to_pay_input, loan_mint_decimals),
+            borrower_dassi_coin_account_to_debit.key.to_string()
+        );
+
+        invoke(
+            &transfer_emi_amount_to_vault_ix,
+            &[
+                borrower_dassi_coin_account_to_debit.clone(),
+                dassi_coin_vault_account.clone(),
+                borrower_main_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let dassi_coin_vault_account_data_after =
+            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
         let dassi_coin_vault_balance_after = dassi_coin_vault_account_data_after.amount;
         msg!(
             "DassiCoin vault balance after: {}",
@@ -433,12 +793,6 @@ impl Processor {
             return Err(DassiError::ExpectedAmountMismatch.into());
         }
 
-        let loan_info_storage_account = next_account_info(account_info_iter)?;
-
-        if loan_info_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-
         // update lender payment in LoanInfoAccData
         let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
         let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
@@ -446,108 +800,90 @@ impl Processor {
                 [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
         )
         .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
 
         if loan_info_header_data.acc_type != AccTypes::LoanInfoAcc as u8 {
             return Err(DassiError::ExpectedAccountTypeMismatched.into());
         }
 
-        if loan_info_header_data
-            .total_loan_amount
-            .checked_div(loan_info_header_data.number_of_emis_needed_to_repay_the_loan as u64)
-            .unwrap()
-            > emi_amount_to_pay_input
-        {
-            return Err(DassiError::ExpectedAmountMismatch.into());
+        match Self::get_loan_status(&loan_info_header_data)? {
+            LoanStatus::Disbursed | LoanStatus::Repaying => {}
+            LoanStatus::Repaid | LoanStatus::Closed => {
+                return Err(DassiError::LoanAlreadyPaid.into());
+            }
+            // after default outstanding balance is paid by guarantor
+            LoanStatus::Defaulted => return Err(DassiError::LoanAlreadyDefaulted.into()),
+            _ => return Err(DassiError::UnexpectedLoanStatus.into()),
         }
 
-        if loan_info_header_data.repaid_amount_by_borrower
-            >= loan_info_header_data.total_loan_amount
+        let mut borrower_data =
+            BorrowerAccount::unpack(&borrower_storage_account.data.try_borrow().unwrap())?;
+        if borrower_data.acc_type != AccTypes::BorrowerAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if borrower_data.borrower_main_acc_pubkey != *borrower_main_account.key
+            || loan_info_header_data.borrower_main_acc_pubkey != *borrower_main_account.key
         {
-            return Err(DassiError::LoanAlreadyPaid.into());
+            return Err(DassiError::BorrowerAccountMismatched.into());
         }
-        loan_info_header_data.repaid_amount_by_borrower = loan_info_header_data
-            .repaid_amount_by_borrower
-            .checked_add(vault_balance_increased)
-            .unwrap();
 
-        let loan_info_repayment_timestamp_si = 9116usize
-            + (loan_info_header_data.next_index_to_store_repayment_info as usize) * (16usize);
-        let loan_info_repayment_timestamp_ei = loan_info_repayment_timestamp_si + 8usize;
-        let loan_info_repayment_amount_ei = loan_info_repayment_timestamp_ei + 8usize;
         let now = Clock::get()?.unix_timestamp as u64;
-        loan_info_data_byte_array
-            [loan_info_repayment_timestamp_si..loan_info_repayment_timestamp_ei]
-            .copy_from_slice(&now.to_le_bytes());
-        loan_info_data_byte_array[loan_info_repayment_timestamp_ei..loan_info_repayment_amount_ei]
-            .copy_from_slice(&vault_balance_increased.to_le_bytes());
-        loan_info_header_data.next_index_to_store_repayment_info = loan_info_header_data
-            .next_index_to_store_repayment_info
-            .checked_add(1u8)
-            .unwrap();
-
-            let lenders_data_storage_account = next_account_info(account_info_iter)?;
-
-            if lenders_data_storage_account.owner != program_id {
-                return Err(DassiError::WrongAccountPassed.into());
-            }
-    
-            let mut lenders_storage_data_byte_array =
-                lenders_data_storage_account.try_borrow_mut_data()?;
-    
-            if lenders_storage_data_byte_array[0] != AccTypes::LendersAcc as u8 {
-                return Err(DassiError::ExpectedAccountTypeMismatched.into());
-            }
-    
-            if lenders_storage_data_byte_array[1] != 1u8 {
-                return Err(DassiError::ExpectedLendersAccNumNotMatched.into());
-            }
-    
-        let emi_amount_distributed_per_lender: u64 = vault_balance_increased.checked_div(loan_info_header_data.next_index_to_store_lender_data as u64).unwrap();
+        let (principal_amount, interest_amount, late_fee_amount) =
+            Self::apply_payment_to_emi_schedule(
+                &mut loan_info_data_byte_array,
+                &mut loan_info_header_data,
+                &mut borrower_data.credit_score,
+                vault_balance_increased,
+                now,
+                config,
+            )?;
+        msg!(
+            "emi principal amount {}, interest amount {}, late fee amount {}",
+            principal_amount,
+            interest_amount,
+            late_fee_amount
+        );
+        msg!("borrower credit score {}", borrower_data.credit_score);
 
-        for i in 0..loan_info_header_data.next_index_to_store_lender_data {
-            let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
-            + (i as usize)
-                * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
-        let loan_info_lender_data_ei: usize =
-            loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
-        let loan_info_lender_data: LoanInfoAccLendersData =
-            state::unpack_to_loan_info_acc_lender_data(
-                &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
-            )
-            .unwrap();
-        
+        BorrowerAccount::pack(
+            borrower_data,
+            &mut borrower_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
 
-        // lender_id_input can vary from 0 to 49_999 included
-        let lender_si_in_lenders_data_byte_array: usize = 2usize
-            + (loan_info_lender_data.lender_id as usize)
-                .checked_mul(state::LENDER_ACC_DATA_SIZE)
-                .unwrap();
-        let lender_ei_in_lenders_data_byte_array: usize =
-            lender_si_in_lenders_data_byte_array + state::LENDER_ACC_DATA_SIZE;
-        let mut lender_acc_data: LenderAccountData = state::unpack_to_lender_account_data(
-            &lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
-        )
-        .unwrap();
+        // late fees are earnings of lenders just like interest
+        let lenders_earning_amount = interest_amount.checked_add(late_fee_amount).unwrap();
 
-        // update lender data in LendersAccountDataArray
-        lender_acc_data.total_lending_amount = lender_acc_data
-            .total_lending_amount
-            .checked_add(vault_balance_increased as u128)
-            .unwrap();
-        lender_acc_data.total_unique_lending_amount = lender_acc_data
-            .total_unique_lending_amount
+        loan_info_header_data.repaid_amount_by_borrower = loan_info_header_data
+            .repaid_amount_by_borrower
             .checked_add(vault_balance_increased)
             .unwrap();
 
-        lender_acc_data.amount_to_withdraw_or_lend = lender_acc_data.amount_to_withdraw_or_lend.checked_add(emi_amount_distributed_per_lender).unwrap();
+        Self::add_pending_lenders_credit(
+            &mut loan_info_header_data,
+            vault_balance_increased,
+            lenders_earning_amount,
+        );
 
-        state::pack_to_lender_account_data(
-            lender_acc_data,
-            &mut lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
-        )
-        .unwrap();
+        // loan is fully repaid, guarantor doesn't need to back it anymore. Guarantor stake account is passed only when loan
+        // has locked stake
+        if loan_info_header_data.next_index_to_store_repayment_info
+            >= loan_info_header_data.number_of_emis_needed_to_repay_the_loan
+        {
+            Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Repaid)?;
+            if loan_info_header_data.guarantor_stake_locked_amount > 0 {
+                let guarantor_stake_account = next_account_info(account_info_iter)?;
+                Self::release_guarantor_stake(
+                    guarantor_stake_account,
+                    &mut loan_info_header_data,
+                    program_id,
+                )?;
+            }
+        } else if Self::get_loan_status(&loan_info_header_data)? == LoanStatus::Disbursed {
+            Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Repaying)?;
         }
 
         state::pack_to_loan_info_header(
@@ -560,14 +896,34 @@ impl Processor {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn initialize_loan_info_account(
         accounts: &[AccountInfo],
         num_days_left_for_first_repayment_input: u16,
         num_emis_needed_to_repay_the_loan_input: u16,
         num_days_for_fundraising_input: u16,
         total_loan_amount_input: u64,
+        annual_interest_rate_bps_input: u16,
+        amortization_type_input: u8,
+        repayment_interval_type_input: u8,
+        custom_repayment_interval_days_input: u16,
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let emi_interval_days = RepaymentIntervalType::from_u8(repayment_interval_type_input)
+            .and_then(|interval_type| {
+                interval_type.get_emi_interval_days(custom_repayment_interval_days_input)
+            })
+            .ok_or(DassiError::InvalidLoanParameters)?;
+
+        if num_emis_needed_to_repay_the_loan_input == 0
+            || num_emis_needed_to_repay_the_loan_input > state::MAX_NUMBER_OF_EMIS as u16
+            || annual_interest_rate_bps_input > amortization::MAX_ANNUAL_INTEREST_RATE_BPS
+            || AmortizationType::from_u8(amortization_type_input).is_none()
+        {
+            return Err(DassiError::InvalidLoanParameters.into());
+        }
+
         // also update borrower storage account
         let account_info_iter = &mut accounts.iter();
         let guarantor_main_account = next_account_info(account_info_iter)?;
@@ -575,51 +931,17 @@ impl Processor {
         if !guarantor_main_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
-       let borrower_main_account = next_account_info(account_info_iter)?;
+        let borrower_main_account = next_account_info(account_info_iter)?;
 
         let loan_info_storage_account = next_account_info(account_info_iter)?;
 
-        // just for extra safety, even this check is not required
-        if loan_info_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-
-        let rent = Rent::get()?;
-
-        if !rent.is_exempt(
-            loan_info_storage_account.lamports(),
-            loan_info_storage_account.data_len(),
-        ) {
-            return Err(DassiError::NotRentExempt.into());
-        }
-
-        if loan_info_storage_account.data_len() != state::LOAN_INFO_ACC_DATA_SIZE {
-            return Err(DassiError::DataSizeNotMatched.into());
-        }
-
-        let mut loan_info_data_byte_array = loan_info_storage_account.data.borrow_mut();
-
-        if loan_info_data_byte_array[0] != 0 {
-            return Err(DassiError::LoanInfoDataAlreadyInitialized.into());
-        }
-
         let borrower_storage_account = next_account_info(account_info_iter)?;
-        if borrower_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-
-        /*
-        let expected_borrower_storage_account_pubkey = Pubkey::create_with_seed(
+        validation::check_borrower_storage_account(
+            borrower_storage_account,
             borrower_main_account.key,
-            "DassiFinanceBorrower",
             program_id,
         )?;
 
-        if expected_borrower_storage_account_pubkey != *borrower_storage_account.key {
-            return Err(DassiError::AccountMismatched.into());
-        }
-        */
-
         let mut borrower_data =
             BorrowerAccount::unpack(&borrower_storage_account.data.try_borrow().unwrap())?;
         if borrower_data.is_active_loan != 0 {
@@ -628,12 +950,108 @@ impl Processor {
         if borrower_data.acc_type != AccTypes::BorrowerAcc as u8 {
             return Err(DassiError::ExpectedAccountTypeMismatched.into());
         }
+
+        // every loan of a borrower gets its own account, derived from the borrower's next loan nonce
+        let loan_nonce = borrower_data.next_loan_nonce;
+        let (expected_loan_info_storage_account_pubkey, loan_info_bump_seed) =
+            utils::get_loan_info_address(borrower_main_account.key, loan_nonce, program_id);
+
+        if expected_loan_info_storage_account_pubkey != *loan_info_storage_account.key {
+            return Err(DassiError::AccountMismatched.into());
+        }
+
+        if loan_info_storage_account.data_len() != 0 {
+            return Err(DassiError::LoanInfoDataAlreadyInitialized.into());
+        }
+
+        borrower_data.is_active_loan = 1u8;
         borrower_data.active_loan_address = *loan_info_storage_account.key;
+        borrower_data.next_loan_nonce = loan_nonce.checked_add(1).unwrap();
         BorrowerAccount::pack(
             borrower_data,
             &mut borrower_storage_account.data.try_borrow_mut().unwrap(),
         )?;
 
+        let guarantor_storage_account = next_account_info(account_info_iter)?;
+        validation::check_guarantor_storage_account(
+            guarantor_storage_account,
+            guarantor_main_account.key,
+            program_id,
+        )?;
+
+        let guarantor_data =
+            GuarantorAccount::unpack(&guarantor_storage_account.data.try_borrow().unwrap())?;
+        if guarantor_data.acc_type != AccTypes::GuarantorAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if guarantor_data.guarantor_main_acc_pubkey != *guarantor_main_account.key {
+            return Err(DassiError::GuarantorAccountMismatched.into());
+        }
+
+        let loan_mint_account = next_account_info(account_info_iter)?;
+        let loan_mint_data = validation::unpack_loan_mint(loan_mint_account, config)?;
+
+        // loan must be big enough to take at least one lending of min lending amount
+        let min_lending_amount = Self::get_min_lending_amount(loan_mint_data.decimals, config)?;
+        if total_loan_amount_input == 0 || total_loan_amount_input < min_lending_amount {
+            return Err(DassiError::InvalidLoanParameters.into());
+        }
+
+        let payer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // stake is locked in loan mint, so on default it can be slashed into vault of loan mint as it is
+        let required_stake_amount: u64 = ((total_loan_amount_input as u128)
+            .checked_mul(config.params.guarantor_stake_coverage_bps as u128)
+            .unwrap()
+            / amortization::BASIS_POINTS) as u64;
+        if required_stake_amount > 0 {
+            let guarantor_stake_account = next_account_info(account_info_iter)?;
+            let mut guarantor_stake_data = Self::load_guarantor_stake_account(
+                guarantor_stake_account,
+                guarantor_main_account.key,
+                loan_mint_account.key,
+                program_id,
+            )?;
+            let free_stake_amount = guarantor_stake_data
+                .staked_amount
+                .checked_sub(guarantor_stake_data.locked_stake_amount)
+                .unwrap();
+            msg!(
+                "required guarantor stake {}, free guarantor stake {}",
+                required_stake_amount,
+                free_stake_amount
+            );
+            if free_stake_amount < required_stake_amount {
+                return Err(DassiError::InsufficientGuarantorStake.into());
+            }
+            guarantor_stake_data.locked_stake_amount = guarantor_stake_data
+                .locked_stake_amount
+                .checked_add(required_stake_amount)
+                .unwrap();
+            GuarantorStakeAccount::pack(
+                guarantor_stake_data,
+                &mut guarantor_stake_account.data.try_borrow_mut().unwrap(),
+            )?;
+        }
+
+        msg!("Calling the system program to create loan info storage account...");
+        Self::create_program_account(
+            payer_account,
+            loan_info_storage_account,
+            system_program,
+            state::LOAN_INFO_ACC_DATA_SIZE,
+            &[
+                &b"DassiFinanceLoan"[..],
+                borrower_main_account.key.as_ref(),
+                &loan_nonce.to_le_bytes(),
+                &[loan_info_bump_seed],
+            ],
+            program_id,
+        )?;
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.data.borrow_mut();
+
         let num_seconds_in_one_day: u64 = 86400u64;
         let now = Clock::get()?.unix_timestamp as u64;
         let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_unchecked_to_loan_info_header(
@@ -642,6 +1060,7 @@ impl Processor {
         )
         .unwrap();
         loan_info_header_data.acc_type = AccTypes::LoanInfoAcc as u8;
+        loan_info_header_data.loan_status = LoanStatus::Fundraising as u8;
         loan_info_header_data.borrower_main_acc_pubkey = *borrower_main_account.key;
         loan_info_header_data.guarantor_main_acc_pubkey = *guarantor_main_account.key;
         loan_info_header_data.loan_approval_timestamp = now.clone();
@@ -654,16 +1073,26 @@ impl Processor {
             .unwrap();
         loan_info_header_data.fundraising_period_ending_timestamp =
             calculate_fundraising_period_ending_timestamp;
-        // a user can pay upto 5 days late, after that his credit score will decrease
-        loan_info_header_data.first_repayment_last_date_timestamp =
-            (num_days_left_for_first_repayment_input
-                .checked_add(5u16)
-                .unwrap() as u64)
-                .checked_mul(num_seconds_in_one_day)
-                .unwrap();
+        // a user can pay upto late_payment_grace_period_days of config after due date, after that emi is late and his credit
+        // score will decrease
+        loan_info_header_data.first_repayment_last_date_timestamp = now
+            .checked_add(
+                (num_days_left_for_first_repayment_input as u64)
+                    .checked_mul(num_seconds_in_one_day)
+                    .unwrap(),
+            )
+            .unwrap();
         loan_info_header_data.total_loan_amount = total_loan_amount_input;
         loan_info_header_data.number_of_emis_needed_to_repay_the_loan =
             num_emis_needed_to_repay_the_loan_input as u8;
+        loan_info_header_data.annual_interest_rate_bps = annual_interest_rate_bps_input;
+        loan_info_header_data.amortization_type = amortization_type_input;
+        loan_info_header_data.repayment_interval_type = repayment_interval_type_input;
+        loan_info_header_data.emi_interval_days = emi_interval_days;
+        loan_info_header_data.guarantor_stake_locked_amount = required_stake_amount;
+        loan_info_header_data.loan_nonce = loan_nonce;
+        loan_info_header_data.loan_mint = *loan_mint_account.key;
+        loan_info_header_data.loan_mint_decimals = loan_mint_data.decimals;
 
         state::pack_to_loan_info_header(
             loan_info_header_data,
@@ -678,7 +1107,6 @@ impl Processor {
     // This will credit all free funds to lender wallet
     fn process_withdraw_lender_free_wallet_funds(
         accounts: &[AccountInfo],
-        lender_id_input: u32,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -689,102 +1117,296 @@ impl Processor {
         }
 
         let lender_dassi_coin_account_to_credit = next_account_info(account_info_iter)?;
-
         // dassi_coin_vault_account is program controlled vault account and can be only controlled by our deployed program for debit funds or any kind of operation
         let dassi_coin_vault_account = next_account_info(account_info_iter)?;
 
-        /*
-        check if right vault is passed 
-        */
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, lender_main_account.key, program_id)?;
 
-        let lenders_data_storage_account = next_account_info(account_info_iter)?;
+        // free balance is paid out of vault of its mint
+        validation::validate_user_token_account(
+            lender_dassi_coin_account_to_credit,
+            lender_main_account.key,
+            &lender_data.mint,
+        )?;
+        let dassi_coin_vault_account_data_before =
+            validation::validate_mint_vault(dassi_coin_vault_account, &lender_data.mint, program_id)?;
 
-        if lenders_data_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
+        let withdraw_amount: u64 = lender_data.amount_to_withdraw_or_lend;
+        lender_data.total_unique_lending_amount = lender_data
+            .total_unique_lending_amount
+            .checked_sub(withdraw_amount)
+            .unwrap();
+        lender_data.amount_to_withdraw_or_lend = 0u64;
 
-        let mut lenders_storage_data_byte_array =
-            lenders_data_storage_account.try_borrow_mut_data()?;
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
 
-        if lenders_storage_data_byte_array[0] != AccTypes::LendersAcc as u8 {
-            return Err(DassiError::ExpectedAccountTypeMismatched.into());
-        }
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
 
-        if lenders_storage_data_byte_array[1] != 1u8 {
-            return Err(DassiError::ExpectedLendersAccNumNotMatched.into());
-        }
+        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
 
-        if lender_id_input > 49_999u32 {
-            return Err(DassiError::InvalidLenderIdInput.into());
+        let pda_account = next_account_info(account_info_iter)?;
+
+        // we can also store bump_seed to save computations
+        let (pda, bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        if pda != *pda_account.key {
+            return Err(DassiError::PdaAccountDoesNotMatched.into());
         }
 
-        // lender_id_input can vary from 0 to 49_999 included
-        let lender_si_in_lenders_data_byte_array: usize = 2usize
-            + (lender_id_input as usize)
-                .checked_mul(state::LENDER_ACC_DATA_SIZE)
-                .unwrap();
-        let lender_ei_in_lenders_data_byte_array: usize =
-            lender_si_in_lenders_data_byte_array + state::LENDER_ACC_DATA_SIZE;
-        let mut lender_acc_data: LenderAccountData = state::unpack_to_lender_account_data(
-            &lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
+        let transfer_withdraw_amount_to_lender_ix = spl_token::instruction::transfer(
+            token_program.key,
+            dassi_coin_vault_account.key,
+            lender_dassi_coin_account_to_credit.key,
+            &pda,
+            &[&pda],
+            withdraw_amount,
+        )?;
+        msg!("Calling the token program to transfer withdraw amount to lender...");
+        msg!(
+            "amount of tokens to transfer {}, lender credit key {}",
+            withdraw_amount,
+            lender_dassi_coin_account_to_credit.key.to_string()
+        );
+        invoke_signed(
+            &transfer_withdraw_amount_to_lender_ix,
+            &[
+                dassi_coin_vault_account.clone(),
+                lender_dassi_coin_account_to_credit.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"DassiFinance"[..], &[bump_seed]]],
+        )?;
+
+        let dassi_coin_vault_account_data_after =
+            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
+        let dassi_coin_vault_balance_after = dassi_coin_vault_account_data_after.amount;
+        msg!(
+            "DassiCoin vault balance after: {}",
+            dassi_coin_vault_balance_after
+        );
+
+        let vault_balance_decreased = dassi_coin_vault_balance_before
+            .checked_sub(dassi_coin_vault_balance_after)
+            .unwrap();
+
+        if vault_balance_decreased != withdraw_amount {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    // Lender takes back part or all of what he lent in one slot of a loan which is still raising funds. Cancelled amount is
+    // sent straight back to lender's token account, as if it was never lent
+    fn process_cancel_lending_commitment(
+        accounts: &[AccountInfo],
+        lender_index_input: u8,
+        amount_to_cancel_input: u64,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let lender_main_account = next_account_info(account_info_iter)?;
+
+        if !lender_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let lender_dassi_coin_account_to_credit = next_account_info(account_info_iter)?;
+        let dassi_coin_vault_account = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
         )
         .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+        let loan_mint = loan_info_header_data.loan_mint;
+        let loan_mint_decimals = loan_info_header_data.loan_mint_decimals;
 
-        if lender_acc_data.is_account_active != 1u8
-            && lender_acc_data.lender_main_acc_pubkey != *lender_main_account.key
-        {
-            return Err(DassiError::InvalidLenderIdInput.into());
+        validation::validate_user_token_account(
+            lender_dassi_coin_account_to_credit,
+            lender_main_account.key,
+            &loan_mint,
+        )?;
+        let dassi_coin_vault_account_data_before =
+            validation::validate_mint_vault(dassi_coin_vault_account, &loan_mint, program_id)?;
+
+        // once loan is funded lent amounts belong to borrower
+        if Self::get_loan_status(&loan_info_header_data)? != LoanStatus::Fundraising {
+            return Err(DassiError::BorrowerAlreadyFunded.into());
         }
 
-        let withdraw_amount: u64 = lender_acc_data.amount_to_withdraw_or_lend;
-        lender_acc_data.total_unique_lending_amount = lender_acc_data
-            .total_unique_lending_amount
-            .checked_sub(withdraw_amount.clone())
+        let now = Clock::get()?.unix_timestamp as u64;
+
+        // after fundraising period lenders get their funds back with ReturnFundsToLenders
+        if loan_info_header_data.fundraising_period_ending_timestamp < now {
+            return Err(DassiError::FundraisingPeriodExpired.into());
+        }
+
+        if lender_index_input >= loan_info_header_data.next_index_to_store_lender_data {
+            return Err(DassiError::InvalidLenderIndexInput.into());
+        }
+
+        let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+            + (lender_index_input as usize) * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+        let loan_info_lender_data_ei: usize =
+            loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+        let mut loan_info_lender_data: LoanInfoAccLendersData =
+            state::unpack_to_loan_info_acc_lender_data(
+                &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+            )
             .unwrap();
-        lender_acc_data.amount_to_withdraw_or_lend = 0u64;
 
-        state::pack_to_lender_account_data(
-            lender_acc_data,
-            &mut lenders_storage_data_byte_array
-                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
+        // slot can have moved since caller read its index, so another lender's slot must not be cancelled in its place
+        if loan_info_lender_data.lender_main_acc_pubkey != *lender_main_account.key {
+            return Err(DassiError::LenderAccountMismatched.into());
+        }
+
+        let remaining_lent_amount = loan_info_lender_data
+            .lent_amount
+            .checked_sub(amount_to_cancel_input)
+            .ok_or(DassiError::ExpectedAmountMismatch)?;
+
+        if amount_to_cancel_input == 0 {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        // Whole slot can always be cancelled, its amount is not a multiple of lending unit when it was clamped to what loan
+        // needed. After a partial cancel what stays lent must still be a valid lending
+        if remaining_lent_amount > 0 {
+            let min_lending_amount = Self::get_min_lending_amount(loan_mint_decimals, config)?;
+            if amount_to_cancel_input.checked_rem(Self::get_lending_amount_unit(loan_mint_decimals)?)
+                != Some(0)
+                || remaining_lent_amount < min_lending_amount
+            {
+                return Err(DassiError::ExpectedAmountMismatch.into());
+            }
+        }
+
+        if remaining_lent_amount > 0 {
+            loan_info_lender_data.lent_amount = remaining_lent_amount;
+            state::pack_to_loan_info_acc_lender_data(
+                loan_info_lender_data,
+                &mut loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+            )
+            .unwrap();
+        } else {
+            // slot is removed so that pro rata shares and dust are split only between lenders who still have funds in loan.
+            // Nothing is refunded or credited while loan is raising funds, so lender indexes can still change
+            let last_lender_index = loan_info_header_data.next_index_to_store_lender_data - 1;
+            let last_loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+                + (last_lender_index as usize) * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let last_loan_info_lender_data_ei: usize =
+                last_loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            loan_info_data_byte_array.copy_within(
+                last_loan_info_lender_data_si..last_loan_info_lender_data_ei,
+                loan_info_lender_data_si,
+            );
+            loan_info_data_byte_array[last_loan_info_lender_data_si..last_loan_info_lender_data_ei]
+                .fill(0);
+            loan_info_header_data.next_index_to_store_lender_data = last_lender_index;
+        }
+
+        loan_info_header_data.total_amount_lended = loan_info_header_data
+            .total_amount_lended
+            .checked_sub(amount_to_cancel_input)
+            .unwrap();
+
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
         )
         .unwrap();
 
-        let token_program = next_account_info(account_info_iter)?;
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mut lender_data =
+            Self::load_lender_account(lender_storage_account, lender_main_account.key, program_id)?;
+        if lender_data.mint != loan_mint {
+            return Err(DassiError::MintDoesNotMatched.into());
+        }
+
+        lender_data.total_lending_amount = lender_data
+            .total_lending_amount
+            .checked_sub(amount_to_cancel_input as u128)
+            .unwrap();
+        lender_data.total_unique_lending_amount = lender_data
+            .total_unique_lending_amount
+            .checked_sub(amount_to_cancel_input)
+            .unwrap();
+
+        LenderAccount::pack(
+            lender_data,
+            &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        let lender_position_account = next_account_info(account_info_iter)?;
+        let mut lender_position_data = Self::load_lender_position_account(
+            lender_position_account,
+            lender_main_account.key,
+            loan_info_storage_account.key,
+            program_id,
+        )?;
 
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
+        lender_position_data.principal_lent = lender_position_data
+            .principal_lent
+            .checked_sub(amount_to_cancel_input)
+            .unwrap();
+        if lender_position_data.principal_lent == 0 {
+            lender_position_data.position_status = LenderPositionStatus::Cancelled as u8;
         }
 
-        let dassi_coin_vault_account_data_before =
-            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
-        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
+        LenderPositionAccount::pack(
+            lender_position_data,
+            &mut lender_position_account.data.try_borrow_mut().unwrap(),
+        )?;
 
         let pda_account = next_account_info(account_info_iter)?;
 
-        // we can also store bump_seed to save computations
         let (pda, bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
         if pda != *pda_account.key {
             return Err(DassiError::PdaAccountDoesNotMatched.into());
         }
 
-        let transfer_withdraw_amount_to_lender_ix = spl_token::instruction::transfer(
+        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
+
+        let transfer_cancelled_amount_to_lender_ix = spl_token::instruction::transfer(
             token_program.key,
             dassi_coin_vault_account.key,
             lender_dassi_coin_account_to_credit.key,
             &pda,
             &[&pda],
-            withdraw_amount,
+            amount_to_cancel_input,
         )?;
-        msg!("Calling the token program to transfer withdraw amount to lender...");
+        msg!("Calling the token program to transfer cancelled lending amount to lender...");
         msg!(
-            "amount of dassi coin tokens to transfer {}, lender credit key {}",
-            (withdraw_amount as f64 / DASSI_COIN_DECIMALS as f64),
+            "amount of tokens to transfer {}, lender credit key {}",
+            spl_token::amount_to_ui_amount(amount_to_cancel_input, loan_mint_decimals),
             lender_dassi_coin_account_to_credit.key.to_string()
         );
         invoke_signed(
-            &transfer_withdraw_amount_to_lender_ix,
+            &transfer_cancelled_amount_to_lender_ix,
             &[
                 dassi_coin_vault_account.clone(),
                 lender_dassi_coin_account_to_credit.clone(),
@@ -796,17 +1418,11 @@ impl Processor {
 
         let dassi_coin_vault_account_data_after =
             TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
-        let dassi_coin_vault_balance_after = dassi_coin_vault_account_data_after.amount;
-        msg!(
-            "DassiCoin vault balance after: {}",
-            dassi_coin_vault_balance_after
-        );
-
         let vault_balance_decreased = dassi_coin_vault_balance_before
-            .checked_sub(dassi_coin_vault_balance_after)
+            .checked_sub(dassi_coin_vault_account_data_after.amount)
             .unwrap();
 
-        if vault_balance_decreased != withdraw_amount {
+        if vault_balance_decreased != amount_to_cancel_input {
             return Err(DassiError::ExpectedAmountMismatch.into());
         }
 
@@ -825,23 +1441,10 @@ impl Processor {
         }
 
         let borrower_dassi_ata_to_credit = next_account_info(account_info_iter)?;
-
         let dassi_coin_vault_account = next_account_info(account_info_iter)?;
 
-        /*
-        let dassi_vault_pubkey = utils::get_dassi_vault_pubkey();
-
-        if dassi_vault_pubkey != *dassi_coin_vault_account.key {
-            return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
-        }
-
-        */
-
         let token_program = next_account_info(account_info_iter)?;
-
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
-        }
+        validation::check_token_program(token_program)?;
 
         let loan_info_storage_account = next_account_info(account_info_iter)?;
 
@@ -856,24 +1459,40 @@ impl Processor {
                 [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
         )
         .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
 
         if loan_info_header_data.acc_type != AccTypes::LoanInfoAcc as u8 {
             return Err(DassiError::ExpectedAccountTypeMismatched.into());
         }
 
-        if loan_info_header_data.total_amount_lended < loan_info_header_data.total_loan_amount {
-            return Err(DassiError::CollectedLoanFundsAlreadyWithdrawn.into());
-        }
-
         if loan_info_header_data.borrower_main_acc_pubkey != *borrower_main_account.key {
             return Err(DassiError::BorrowerAccountMismatched.into());
         }
 
-        // borrower can withdraw total_amount_lended, after withdrawing we will set it to 0, so that he can't withdraw second time. We can also use a extra variable to store if funds
-        // withdrawn or not
+        validation::validate_user_token_account(
+            borrower_dassi_ata_to_credit,
+            borrower_main_account.key,
+            &loan_info_header_data.loan_mint,
+        )?;
+        let dassi_coin_vault_account_data_before = validation::validate_mint_vault(
+            dassi_coin_vault_account,
+            &loan_info_header_data.loan_mint,
+            program_id,
+        )?;
+
+        // borrower can withdraw total_amount_lended only once, loan moves from Funded to Disbursed
+        match Self::get_loan_status(&loan_info_header_data)? {
+            LoanStatus::Funded => {}
+            LoanStatus::Fundraising | LoanStatus::Refunding | LoanStatus::Refunded => {
+                return Err(DassiError::UnexpectedLoanStatus.into());
+            }
+            _ => return Err(DassiError::CollectedLoanFundsAlreadyWithdrawn.into()),
+        }
 
-        let dassi_coin_vault_account_data_before =
-            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
         let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
 
         let pda_account = next_account_info(account_info_iter)?;
@@ -894,8 +1513,11 @@ impl Processor {
         )?;
         msg!("Calling the token program to transfer collected loan amount to borrower...");
         msg!(
-            "amount of dassi coin tokens to transfer {}, lender credit key {}",
-            (loan_info_header_data.total_amount_lended as f64 / DASSI_COIN_DECIMALS as f64),
+            "amount of tokens to transfer {}, lender credit key {}",
+            spl_token::amount_to_ui_amount(
+                loan_info_header_data.total_amount_lended,
+                loan_info_header_data.loan_mint_decimals
+            ),
             borrower_dassi_ata_to_credit.key.to_string()
         );
         invoke_signed(
@@ -925,7 +1547,7 @@ impl Processor {
             return Err(DassiError::ExpectedAmountMismatch.into());
         }
 
-        loan_info_header_data.total_amount_lended = 0u64;
+        Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Disbursed)?;
 
         state::pack_to_loan_info_header(
             loan_info_header_data,
@@ -937,96 +1559,9 @@ impl Processor {
         Ok(())
     }
 
-    fn process_transfer_dassi_vault_account_ownership(
-        accounts: &[AccountInfo],
-        program_id: &Pubkey,
-    ) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let initializer_account = next_account_info(account_info_iter)?;
-
-        if !initializer_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let dassi_vault_account = next_account_info(account_info_iter)?;
-
-        let (pda, _nonce) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
-
-        let rent = Rent::get()?;
-
-        if !rent.is_exempt(
-            dassi_vault_account.lamports(),
-            dassi_vault_account.data_len(),
-        ) {
-            return Err(DassiError::NotRentExempt.into());
-        }
-        let token_program = next_account_info(account_info_iter)?;
-
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
-        }
-
-        let dassi_vault_owner_change_ix = spl_token::instruction::set_authority(
-            token_program.key,
-            dassi_vault_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            initializer_account.key,
-            &[&initializer_account.key],
-        )?;
-
-        msg!("Calling the token program to transfer dassi vault account ownership to program...");
-        invoke(
-            &dassi_vault_owner_change_ix,
-            &[
-                dassi_vault_account.clone(),
-                initializer_account.clone(),
-                token_program.clone(),
-            ],
-        )?;
-
-        Ok(())
-    }
-
-    fn process_initialize_lenders_storage_account(accounts: &[AccountInfo]) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let initializer_account = next_account_info(account_info_iter)?;
-
-        if !initializer_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let lenders_storage_account = next_account_info(account_info_iter)?;
-
-        let rent = Rent::get()?;
-
-        if !rent.is_exempt(
-            lenders_storage_account.lamports(),
-            lenders_storage_account.data_len(),
-        ) {
-            return Err(DassiError::NotRentExempt.into());
-        }
-
-        if lenders_storage_account.data_len() != state::LENDERS_STORAGE_ACC_DATA_SIZE {
-            return Err(DassiError::DataSizeNotMatched.into());
-        }
-
-        let mut lenders_storage_data_byte_array =
-            lenders_storage_account.data.try_borrow_mut().unwrap();
-
-        if lenders_storage_data_byte_array[0] != 0 {
-            return Err(DassiError::LendersStorageDataAlreadyInitialized.into());
-        }
-
-        lenders_storage_data_byte_array[0] = AccTypes::LendersAcc as u8;
-        // currently for prototype I'm making every lenders_data_storage_acc_number to 1, but in future when we need more accounts, we have to increment it for every new account generation
-        lenders_storage_data_byte_array[1] = 1u8;
-
-        Ok(())
-    }
-
     fn process_initialize_borrower_storage_account(
         accounts: &[AccountInfo],
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -1036,43 +1571,41 @@ impl Processor {
         }
 
         let borrower_storage_account = next_account_info(account_info_iter)?;
-        if borrower_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-
-        /*
-        let expected_borrower_storage_account_pubkey = Pubkey::create_with_seed(
-            borrower_main_account.key,
-            "DassiFinanceBorrower",
-            program_id,
-        )?;
+        let (expected_borrower_storage_account_pubkey, borrower_storage_bump_seed) =
+            utils::get_borrower_storage_address(borrower_main_account.key, program_id);
 
         if expected_borrower_storage_account_pubkey != *borrower_storage_account.key {
             return Err(DassiError::AccountMismatched.into());
         }
-        */
 
-        let rent = Rent::get()?;
-        if !rent.is_exempt(
-            borrower_storage_account.lamports(),
-            borrower_storage_account.data_len(),
-        ) {
-            return Err(DassiError::NotRentExempt.into());
+        if borrower_storage_account.data_len() != 0 {
+            return Err(DassiError::BorrowerAccountAlreadyInitialized.into());
         }
 
-        // put a condition if borrower_storage_account.data_len() != 75, then error
+        let payer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        msg!("Calling the system program to create borrower storage account...");
+        Self::create_program_account(
+            payer_account,
+            borrower_storage_account,
+            system_program,
+            BorrowerAccount::LEN,
+            &[
+                &b"DassiFinanceBorrower"[..],
+                borrower_main_account.key.as_ref(),
+                &[borrower_storage_bump_seed],
+            ],
+            program_id,
+        )?;
 
         let mut borrower_data =
             BorrowerAccount::unpack_unchecked(&borrower_storage_account.data.borrow())?;
 
-        if borrower_data.is_initialized() {
-            return Err(DassiError::BorrowerAccountAlreadyInitialized.into());
-        }
-
         borrower_data.is_initialized = true;
         borrower_data.acc_type = AccTypes::BorrowerAcc as u8;
         borrower_data.borrower_main_acc_pubkey = *borrower_main_account.key;
-        borrower_data.credit_score = 500_000_000_000u64;
+        borrower_data.credit_score = config.params.initial_credit_score;
 
         BorrowerAccount::pack(
             borrower_data,
@@ -1084,6 +1617,7 @@ impl Processor {
 
     fn process_initialize_guarantor_storage_account(
         accounts: &[AccountInfo],
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -1093,42 +1627,41 @@ impl Processor {
         }
 
         let guarantor_storage_account = next_account_info(account_info_iter)?;
-        if guarantor_storage_account.owner != program_id {
-            return Err(DassiError::WrongAccountPassed.into());
-        }
-
-        /*
-        let expected_guarantor_storage_account_pubkey = Pubkey::create_with_seed(
-            guarantor_main_account.key,
-            "DassiFinanceGuarantor",
-            program_id,
-        )?;
+        let (expected_guarantor_storage_account_pubkey, guarantor_storage_bump_seed) =
+            utils::get_guarantor_storage_address(guarantor_main_account.key, program_id);
 
         if expected_guarantor_storage_account_pubkey != *guarantor_storage_account.key {
             return Err(DassiError::AccountMismatched.into());
         }
-        */
-        let rent = Rent::get()?;
-        if !rent.is_exempt(
-            guarantor_storage_account.lamports(),
-            guarantor_storage_account.data_len(),
-        ) {
-            return Err(DassiError::NotRentExempt.into());
+
+        if guarantor_storage_account.data_len() != 0 {
+            return Err(DassiError::GuarantorAccountAlreadyInitialized.into());
         }
 
-        // put a condition if guarantor_storage_account.data_len() != 75, then error
+        let payer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        msg!("Calling the system program to create guarantor storage account...");
+        Self::create_program_account(
+            payer_account,
+            guarantor_storage_account,
+            system_program,
+            GuarantorAccount::LEN,
+            &[
+                &b"DassiFinanceGuarantor"[..],
+                guarantor_main_account.key.as_ref(),
+                &[guarantor_storage_bump_seed],
+            ],
+            program_id,
+        )?;
 
         let mut guarantor_data =
             GuarantorAccount::unpack_unchecked(&guarantor_storage_account.data.borrow())?;
 
-        if guarantor_data.is_initialized() {
-            return Err(DassiError::GuarantorAccountAlreadyInitialized.into());
-        }
-
         guarantor_data.is_initialized = true;
         guarantor_data.acc_type = AccTypes::GuarantorAcc as u8;
         guarantor_data.guarantor_main_acc_pubkey = *guarantor_main_account.key;
-        guarantor_data.approval_score = 500_000_000_000u64;
+        guarantor_data.approval_score = config.params.initial_credit_score;
 
         GuarantorAccount::pack(
             guarantor_data,
@@ -1138,13 +1671,201 @@ impl Processor {
         Ok(())
     }
 
+    fn process_initialize_lender_storage_account(
+        accounts: &[AccountInfo],
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let lender_main_account = next_account_info(account_info_iter)?;
+        if !lender_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        validation::unpack_loan_mint(mint_account, config)?;
+
+        let (expected_lender_storage_account_pubkey, lender_storage_bump_seed) =
+            utils::get_lender_storage_address(lender_main_account.key, mint_account.key, program_id);
+
+        if expected_lender_storage_account_pubkey != *lender_storage_account.key {
+            return Err(DassiError::AccountMismatched.into());
+        }
+
+        if lender_storage_account.data_len() != 0 {
+            return Err(DassiError::LenderAccountAlreadyInitialized.into());
+        }
+
+        let payer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        msg!("Calling the system program to create lender storage account...");
+        Self::create_program_account(
+            payer_account,
+            lender_storage_account,
+            system_program,
+            LenderAccount::LEN,
+            &[
+                &b"DassiFinanceLender"[..],
+                lender_main_account.key.as_ref(),
+                mint_account.key.as_ref(),
+                &[lender_storage_bump_seed],
+            ],
+            program_id,
+        )?;
+
+        let mut lender_data = LenderAccount::unpack_unchecked(&lender_storage_account.data.borrow())?;
+
+        lender_data.is_initialized = true;
+        lender_data.acc_type = AccTypes::LenderAcc as u8;
+        lender_data.lender_main_acc_pubkey = *lender_main_account.key;
+        lender_data.mint = *mint_account.key;
+
+        LenderAccount::pack(lender_data, &mut lender_storage_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    // Moves a lender out of legacy lenders storage account into his own lender account. Balances are added to lender account
+    // if lender already created it, and slot is cleared so the same balances can't be migrated twice. Legacy balances are in
+    // DassiCoin, so they go to lender account of DassiCoin mint. Legacy storage never tracked interest, so total_interest_earned
+    // only counts interest credited after migration
+    fn process_migrate_lender_account(
+        accounts: &[AccountInfo],
+        lender_id_input: u32,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // migrated free balances are withdrawn from vault of DassiCoin mint, so they must be backed by legacy vault balance
+        if !config.legacy_vault_migrated {
+            return Err(DassiError::LegacyVaultNotMigrated.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let payer_account = next_account_info(account_info_iter)?;
+
+        let lenders_data_storage_account = next_account_info(account_info_iter)?;
+
+        if lenders_data_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        if lenders_data_storage_account.data_len() != state::LEGACY_LENDERS_STORAGE_ACC_DATA_SIZE {
+            return Err(DassiError::DataSizeNotMatched.into());
+        }
+
+        let mut lenders_storage_data_byte_array =
+            lenders_data_storage_account.try_borrow_mut_data()?;
+
+        if lenders_storage_data_byte_array[0] != AccTypes::LendersAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+
+        if lenders_storage_data_byte_array[1] != 1u8 {
+            return Err(DassiError::ExpectedLendersAccNumNotMatched.into());
+        }
+
+        if lender_id_input as usize >= state::LEGACY_MAX_NUMBER_OF_LENDERS {
+            return Err(DassiError::InvalidLenderIdInput.into());
+        }
+
+        let lender_si_in_lenders_data_byte_array: usize = 2usize
+            + (lender_id_input as usize)
+                .checked_mul(state::LEGACY_LENDER_ACC_DATA_SIZE)
+                .unwrap();
+        let lender_ei_in_lenders_data_byte_array: usize =
+            lender_si_in_lenders_data_byte_array + state::LEGACY_LENDER_ACC_DATA_SIZE;
+        let lender_acc_data: LegacyLenderAccountData = state::unpack_to_legacy_lender_account_data(
+            &lenders_storage_data_byte_array
+                [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array],
+        )
+        .unwrap();
+
+        if lender_acc_data.is_account_active != 1u8 {
+            return Err(DassiError::InvalidLenderIdInput.into());
+        }
+
+        let lender_storage_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let lender_main_pubkey = lender_acc_data.lender_main_acc_pubkey;
+        let dassi_coin_mint = config.params.accepted_mint;
+        let mut lender_data = if lender_storage_account.data_len() == 0 {
+            let (expected_lender_storage_account_pubkey, lender_storage_bump_seed) =
+                utils::get_lender_storage_address(&lender_main_pubkey, &dassi_coin_mint, program_id);
+
+            if expected_lender_storage_account_pubkey != *lender_storage_account.key {
+                return Err(DassiError::AccountMismatched.into());
+            }
+
+            msg!("Calling the system program to create lender storage account...");
+            Self::create_program_account(
+                payer_account,
+                lender_storage_account,
+                system_program,
+                LenderAccount::LEN,
+                &[
+                    &b"DassiFinanceLender"[..],
+                    lender_main_pubkey.as_ref(),
+                    dassi_coin_mint.as_ref(),
+                    &[lender_storage_bump_seed],
+                ],
+                program_id,
+            )?;
+
+            let mut lender_data =
+                LenderAccount::unpack_unchecked(&lender_storage_account.data.borrow())?;
+            lender_data.is_initialized = true;
+            lender_data.acc_type = AccTypes::LenderAcc as u8;
+            lender_data.lender_main_acc_pubkey = lender_main_pubkey;
+            lender_data.mint = dassi_coin_mint;
+            lender_data
+        } else {
+            let lender_data =
+                Self::load_lender_account(lender_storage_account, &lender_main_pubkey, program_id)?;
+            if lender_data.mint != dassi_coin_mint {
+                return Err(DassiError::MintDoesNotMatched.into());
+            }
+            lender_data
+        };
+
+        lender_data.total_lending_amount = lender_data
+            .total_lending_amount
+            .checked_add(lender_acc_data.total_lending_amount)
+            .unwrap();
+        lender_data.total_unique_lending_amount = lender_data
+            .total_unique_lending_amount
+            .checked_add(lender_acc_data.total_unique_lending_amount)
+            .unwrap();
+        lender_data.amount_to_withdraw_or_lend = lender_data
+            .amount_to_withdraw_or_lend
+            .checked_add(lender_acc_data.amount_to_withdraw_or_lend)
+            .unwrap();
+
+        LenderAccount::pack(lender_data, &mut lender_storage_account.data.borrow_mut())?;
+
+        lenders_storage_data_byte_array
+            [lender_si_in_lenders_data_byte_array..lender_ei_in_lenders_data_byte_array]
+            .fill(0);
+
+        msg!(
+            "migrated lender {} with lender id {}",
+            lender_main_pubkey.to_string(),
+            lender_id_input
+        );
+
+        Ok(())
+    }
+
     //On each airdrop, users will get 250 DassiCoin test tokens. A user can airdrop a maximum of 10 times.
     fn process_airdrop_users_with_dassi_test_coins(
         accounts: &[AccountInfo],
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
-        let amount_to_airdrop = 500_000_000_000u64;
-        let max_amount_to_airdrop = 2_500_000_000_000u64;
+        let amount_to_airdrop = config.params.airdrop_amount;
+        let max_amount_to_airdrop = config.params.max_airdrop_amount_per_user;
 
         let account_info_iter = &mut accounts.iter();
         let airdrop_user_main_account = next_account_info(account_info_iter)?;
@@ -1193,14 +1914,18 @@ impl Processor {
 
         let user_dassi_coin_associated_token_to_credit_account =
             next_account_info(account_info_iter)?;
+        validation::validate_user_token_account(
+            user_dassi_coin_associated_token_to_credit_account,
+            airdrop_user_main_account.key,
+            &config.params.accepted_mint,
+        )?;
 
         let airdrop_vault_dassi_coin_account = next_account_info(account_info_iter)?;
+        let airdrop_vault_account_data =
+            validation::validate_airdrop_vault(airdrop_vault_dassi_coin_account, config, program_id)?;
 
         let token_program = next_account_info(account_info_iter)?;
-
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
-        }
+        validation::check_token_program(token_program)?;
 
         let pda_account = next_account_info(account_info_iter)?;
 
@@ -1210,9 +1935,6 @@ impl Processor {
             return Err(DassiError::PdaAccountDoesNotMatched.into());
         }
 
-        let airdrop_vault_account_data =
-            TokenAccount::unpack(&airdrop_vault_dassi_coin_account.data.borrow())?;
-
         let airdrop_vault_balance_before = airdrop_vault_account_data.amount;
 
         let transfer_dassi_coin_to_airdroper_ix = spl_token::instruction::transfer(
@@ -1251,16 +1973,20 @@ impl Processor {
 
     fn process_transfer_airdrop_vault_account_ownership(
         accounts: &[AccountInfo],
+        config: &ProtocolConfig,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let initializer_account = next_account_info(account_info_iter)?;
 
-        if !initializer_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        Self::check_role(initializer_account, &config.admin, DassiError::AdminDoesNotMatched)?;
 
         let airdrop_vault_dassi_coin_account = next_account_info(account_info_iter)?;
+        validation::validate_user_token_account(
+            airdrop_vault_dassi_coin_account,
+            initializer_account.key,
+            &config.params.accepted_mint,
+        )?;
 
         let (pda, _nonce) = Pubkey::find_program_address(&[b"DassiFinanceAirdrop"], program_id);
 
@@ -1273,10 +1999,7 @@ impl Processor {
             return Err(DassiError::NotRentExempt.into());
         }
         let token_program = next_account_info(account_info_iter)?;
-
-        if token_program.key != &spl_token::id() {
-            return Err(DassiError::InvalidTokenProgram.into());
-        }
+        validation::check_token_program(token_program)?;
 
         let airdrop_vault_owner_change_ix = spl_token::instruction::set_authority(
             token_program.key,
@@ -1300,19 +2023,2226 @@ impl Processor {
         Ok(())
     }
 
-    fn process_close_loan_info_account(accounts: &[AccountInfo]) -> ProgramResult {
+    // Loan info account can be closed only when loan is fully repaid or fundraising failed and all lenders are refunded,
+    // so that no lender balance is owed from this loan anymore. Rent lamports are sent to the recipient account passed by borrower
+    fn process_close_loan_info_account(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
+        let borrower_main_account = next_account_info(account_info_iter)?;
+
+        if !borrower_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let borrower_storage_account = next_account_info(account_info_iter)?;
+        validation::check_borrower_storage_account(
+            borrower_storage_account,
+            borrower_main_account.key,
+            program_id,
+        )?;
+
+        let rent_recipient_account = next_account_info(account_info_iter)?;
+
+        // lamports moved to loan info account itself would be zeroed right after, burning the rent
+        if rent_recipient_account.key == loan_info_storage_account.key {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+
+        if loan_info_header_data.borrower_main_acc_pubkey != *borrower_main_account.key {
+            return Err(DassiError::BorrowerAccountMismatched.into());
+        }
+
+        let can_close_loan = match Self::get_loan_status(&loan_info_header_data)? {
+            LoanStatus::Repaid | LoanStatus::Refunded => true,
+            // defaulted loan is settled once guarantor paid all principal borrower didn't
+            LoanStatus::Defaulted => {
+                loan_info_header_data
+                    .principal_repaid_by_borrower
+                    .checked_add(loan_info_header_data.guarantor_repaid_amount)
+                    .unwrap()
+                    >= loan_info_header_data.total_loan_amount
+            }
+            _ => false,
+        };
+
+        if !can_close_loan {
+            return Err(DassiError::LenderBalanceStillOwed.into());
+        }
+
+        if loan_info_header_data.lenders_pending_credit_amount > 0
+            || loan_info_header_data.lenders_round_credit_amount > 0
+        {
+            return Err(DassiError::RepaymentsNotCreditedToLenders.into());
+        }
+
+        // stake of repaid loan is released on last emi and of defaulted loan when it is slashed, so only refunded loan has it
+        // here. Guarantor stake account is passed only in that case, otherwise next account is protocol config
+        if loan_info_header_data.guarantor_stake_locked_amount > 0 {
+            let guarantor_stake_account = next_account_info(account_info_iter)?;
+            Self::release_guarantor_stake(
+                guarantor_stake_account,
+                &mut loan_info_header_data,
+                program_id,
+            )?;
+        }
+
+        let mut borrower_data =
+            BorrowerAccount::unpack(&borrower_storage_account.data.try_borrow().unwrap())?;
+
+        if borrower_data.acc_type != AccTypes::BorrowerAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+
+        if borrower_data.borrower_main_acc_pubkey != *borrower_main_account.key {
+            return Err(DassiError::BorrowerAccountMismatched.into());
+        }
+
+        // borrower can take another loan after closing this one
+        if borrower_data.active_loan_address == *loan_info_storage_account.key {
+            borrower_data.is_active_loan = 0u8;
+            borrower_data.active_loan_address = Pubkey::default();
+            BorrowerAccount::pack(
+                borrower_data,
+                &mut borrower_storage_account.data.try_borrow_mut().unwrap(),
+            )?;
+        }
+
+        Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Closed)?;
+
+        // header is kept with Closed status so that account can't be initialized again in same transaction
+        loan_info_data_byte_array.fill(0);
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+
+        let loan_info_storage_account_lamports = loan_info_storage_account.lamports();
+        **rent_recipient_account.lamports.borrow_mut() = rent_recipient_account
+            .lamports()
+            .checked_add(loan_info_storage_account_lamports)
+            .ok_or(DassiError::AmountOverflow)?;
+        **loan_info_storage_account.lamports.borrow_mut() = 0u64;
+
+        msg!(
+            "closed loan info account, {} lamports sent to {}",
+            loan_info_storage_account_lamports,
+            rent_recipient_account.key.to_string()
+        );
 
         Ok(())
     }
 
+    // Fundraising period expired and loan is not fulfilled, so credit every lender's lent amount back to his free wallet in
+    // his lender account. Refund is done for num_accounts_input lenders per call, next_index_to_return_funds keeps track of
+    // lenders already refunded so no lender can be refunded twice
     fn process_return_funds_to_lenders(
         accounts: &[AccountInfo],
         num_accounts_input: u16,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
+        let fee_payer_account = next_account_info(account_info_iter)?;
+
+        if !fee_payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if num_accounts_input == 0 {
+            return Err(DassiError::InvalidInstruction.into());
+        }
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+
+        match Self::get_loan_status(&loan_info_header_data)? {
+            LoanStatus::Fundraising => {
+                let now = Clock::get()?.unix_timestamp as u64;
+
+                if loan_info_header_data.fundraising_period_ending_timestamp >= now {
+                    return Err(DassiError::FundraisingPeriodNotExpired.into());
+                }
+
+                Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Refunding)?;
+            }
+            LoanStatus::Refunding => {}
+            LoanStatus::Refunded | LoanStatus::Closed => {
+                return Err(DassiError::FundsAlreadyReturnedToLenders.into());
+            }
+            _ => return Err(DassiError::BorrowerAlreadyFunded.into()),
+        }
+
+        let last_index_to_return_funds: u8 = (loan_info_header_data.next_index_to_return_funds
+            as u16)
+            .checked_add(num_accounts_input)
+            .unwrap()
+            .min(loan_info_header_data.next_index_to_store_lender_data as u16)
+            as u8;
+
+        for i in loan_info_header_data.next_index_to_return_funds..last_index_to_return_funds {
+            let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+                + (i as usize) * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data_ei: usize =
+                loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data: LoanInfoAccLendersData =
+                state::unpack_to_loan_info_acc_lender_data(
+                    &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+                )
+                .unwrap();
+
+            // lender accounts are passed in same order as lenders are stored in loan account
+            let lender_storage_account = next_account_info(account_info_iter)?;
+            let mut lender_data = Self::load_lender_account(
+                lender_storage_account,
+                &loan_info_lender_data.lender_main_acc_pubkey,
+                program_id,
+            )?;
+            if lender_data.mint != loan_info_header_data.loan_mint {
+                return Err(DassiError::MintDoesNotMatched.into());
+            }
+
+            lender_data.amount_to_withdraw_or_lend = lender_data
+                .amount_to_withdraw_or_lend
+                .checked_add(loan_info_lender_data.lent_amount)
+                .unwrap();
+
+            msg!(
+                "returning {} tokens to lender {}",
+                spl_token::amount_to_ui_amount(
+                    loan_info_lender_data.lent_amount,
+                    loan_info_header_data.loan_mint_decimals
+                ),
+                loan_info_lender_data.lender_main_acc_pubkey.to_string()
+            );
+
+            LenderAccount::pack(
+                lender_data,
+                &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+            )?;
+
+            let lender_position_account = next_account_info(account_info_iter)?;
+            let mut lender_position_data = Self::load_lender_position_account(
+                lender_position_account,
+                &loan_info_lender_data.lender_main_acc_pubkey,
+                loan_info_storage_account.key,
+                program_id,
+            )?;
+            lender_position_data.principal_returned = lender_position_data
+                .principal_returned
+                .checked_add(loan_info_lender_data.lent_amount)
+                .unwrap();
+            lender_position_data.position_status = LenderPositionStatus::Refunded as u8;
+            LenderPositionAccount::pack(
+                lender_position_data,
+                &mut lender_position_account.data.try_borrow_mut().unwrap(),
+            )?;
+        }
+
+        loan_info_header_data.next_index_to_return_funds = last_index_to_return_funds;
+
+        if loan_info_header_data.next_index_to_return_funds
+            >= loan_info_header_data.next_index_to_store_lender_data
+        {
+            Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Refunded)?;
+        }
+
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
 
         Ok(())
     }
+
+    // Anyone can declare a loan defaulted once borrower missed enough emis after their grace period. Borrower's credit score
+    // and guarantor's approval score are lowered, stake locked for this loan is slashed to lenders upto outstanding principal
+    // and from then on guarantor is liable for the rest of outstanding principal
+    fn process_mark_loan_defaulted(
+        accounts: &[AccountInfo],
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let fee_payer_account = next_account_info(account_info_iter)?;
+
+        if !fee_payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let borrower_storage_account = next_account_info(account_info_iter)?;
+
+        let guarantor_storage_account = next_account_info(account_info_iter)?;
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+        validation::check_borrower_storage_account(
+            borrower_storage_account,
+            &loan_info_header_data.borrower_main_acc_pubkey,
+            program_id,
+        )?;
+        validation::check_guarantor_storage_account(
+            guarantor_storage_account,
+            &loan_info_header_data.guarantor_main_acc_pubkey,
+            program_id,
+        )?;
+
+        match Self::get_loan_status(&loan_info_header_data)? {
+            LoanStatus::Disbursed | LoanStatus::Repaying => {}
+            LoanStatus::Defaulted => return Err(DassiError::LoanAlreadyDefaulted.into()),
+            _ => return Err(DassiError::UnexpectedLoanStatus.into()),
+        }
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        let mut number_of_missed_emis: u8 = 0;
+        for emi_index in loan_info_header_data.next_index_to_store_repayment_info
+            ..loan_info_header_data.number_of_emis_needed_to_repay_the_loan
+        {
+            let emi_due_timestamp = amortization::calculate_emi_due_timestamp(
+                loan_info_header_data.first_repayment_last_date_timestamp,
+                loan_info_header_data.emi_interval_days as u64,
+                emi_index,
+            )
+            .ok_or(DassiError::AmountOverflow)?;
+            if credit_score::calculate_days_late(
+                emi_due_timestamp,
+                config.params.late_payment_grace_period_days as u64,
+                now,
+            )
+            .is_none()
+            {
+                break;
+            }
+            number_of_missed_emis += 1;
+        }
+        msg!("number of missed emis {}", number_of_missed_emis);
+
+        if number_of_missed_emis < credit_score::MISSED_EMIS_TO_DECLARE_DEFAULT {
+            return Err(DassiError::LoanNotOverdue.into());
+        }
+
+        let mut borrower_data =
+            BorrowerAccount::unpack(&borrower_storage_account.data.try_borrow().unwrap())?;
+        if borrower_data.acc_type != AccTypes::BorrowerAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if borrower_data.borrower_main_acc_pubkey != loan_info_header_data.borrower_main_acc_pubkey
+        {
+            return Err(DassiError::BorrowerAccountMismatched.into());
+        }
+
+        let mut guarantor_data =
+            GuarantorAccount::unpack(&guarantor_storage_account.data.try_borrow().unwrap())?;
+        if guarantor_data.acc_type != AccTypes::GuarantorAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if guarantor_data.guarantor_main_acc_pubkey
+            != loan_info_header_data.guarantor_main_acc_pubkey
+        {
+            return Err(DassiError::GuarantorAccountMismatched.into());
+        }
+
+        borrower_data.credit_score =
+            credit_score::calculate_score_after_default(borrower_data.credit_score);
+        guarantor_data.approval_score =
+            credit_score::calculate_approval_score_after_default(guarantor_data.approval_score);
+        msg!(
+            "borrower credit score {}, guarantor approval score {}",
+            borrower_data.credit_score,
+            guarantor_data.approval_score
+        );
+
+        BorrowerAccount::pack(
+            borrower_data,
+            &mut borrower_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
+        GuarantorAccount::pack(
+            guarantor_data,
+            &mut guarantor_storage_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Defaulted)?;
+
+        // stake is locked in loan mint, so slashed stake goes to vault of loan mint as it is. Guarantor stake account is
+        // passed only when loan has locked stake
+        let outstanding_principal = loan_info_header_data
+            .total_loan_amount
+            .checked_sub(loan_info_header_data.principal_repaid_by_borrower)
+            .unwrap();
+        let slashed_stake_amount = loan_info_header_data
+            .guarantor_stake_locked_amount
+            .min(outstanding_principal);
+
+        if loan_info_header_data.guarantor_stake_locked_amount > 0 {
+            let guarantor_stake_account = next_account_info(account_info_iter)?;
+            let mut guarantor_stake_data = Self::load_guarantor_stake_account(
+                guarantor_stake_account,
+                &loan_info_header_data.guarantor_main_acc_pubkey,
+                &loan_info_header_data.loan_mint,
+                program_id,
+            )?;
+
+            // whole locked stake is unlocked, slashed part leaves the stake vault and the rest can be unstaked by guarantor
+            guarantor_stake_data.locked_stake_amount = guarantor_stake_data
+                .locked_stake_amount
+                .checked_sub(loan_info_header_data.guarantor_stake_locked_amount)
+                .unwrap();
+            guarantor_stake_data.staked_amount = guarantor_stake_data
+                .staked_amount
+                .checked_sub(slashed_stake_amount)
+                .unwrap();
+            GuarantorStakeAccount::pack(
+                guarantor_stake_data,
+                &mut guarantor_stake_account.data.try_borrow_mut().unwrap(),
+            )?;
+            loan_info_header_data.guarantor_stake_locked_amount = 0u64;
+            loan_info_header_data.guarantor_repaid_amount = loan_info_header_data
+                .guarantor_repaid_amount
+                .checked_add(slashed_stake_amount)
+                .unwrap();
+
+            if slashed_stake_amount > 0 {
+                let guarantor_stake_vault_account = next_account_info(account_info_iter)?;
+                validation::validate_guarantor_stake_vault(
+                    guarantor_stake_vault_account,
+                    guarantor_stake_account.key,
+                    &loan_info_header_data.loan_mint,
+                    program_id,
+                )?;
+
+                let loan_mint_vault_account = next_account_info(account_info_iter)?;
+                let loan_mint_vault_account_data_before = validation::validate_mint_vault(
+                    loan_mint_vault_account,
+                    &loan_info_header_data.loan_mint,
+                    program_id,
+                )?;
+
+                let token_program = next_account_info(account_info_iter)?;
+                validation::check_token_program(token_program)?;
+
+                let pda_account = next_account_info(account_info_iter)?;
+
+                let (pda, bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+                if pda != *pda_account.key {
+                    return Err(DassiError::PdaAccountDoesNotMatched.into());
+                }
+
+                let transfer_slashed_stake_to_vault_ix = spl_token::instruction::transfer(
+                    token_program.key,
+                    guarantor_stake_vault_account.key,
+                    loan_mint_vault_account.key,
+                    &pda,
+                    &[&pda],
+                    slashed_stake_amount,
+                )?;
+                msg!("Calling the token program to transfer slashed guarantor stake to vault...");
+                msg!(
+                    "amount of tokens to transfer {}",
+                    spl_token::amount_to_ui_amount(
+                        slashed_stake_amount,
+                        loan_info_header_data.loan_mint_decimals
+                    )
+                );
+                invoke_signed(
+                    &transfer_slashed_stake_to_vault_ix,
+                    &[
+                        guarantor_stake_vault_account.clone(),
+                        loan_mint_vault_account.clone(),
+                        pda_account.clone(),
+                        token_program.clone(),
+                    ],
+                    &[&[&b"DassiFinance"[..], &[bump_seed]]],
+                )?;
+
+                let loan_mint_vault_account_data_after =
+                    TokenAccount::unpack(&loan_mint_vault_account.data.borrow())?;
+                let vault_balance_increased = loan_mint_vault_account_data_after
+                    .amount
+                    .checked_sub(loan_mint_vault_account_data_before.amount)
+                    .unwrap();
+
+                if vault_balance_increased != slashed_stake_amount {
+                    return Err(DassiError::ExpectedAmountMismatch.into());
+                }
+
+                Self::add_pending_lenders_credit(&mut loan_info_header_data, slashed_stake_amount, 0u64);
+            }
+        }
+
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+
+        Ok(())
+    }
+
+    // Guarantor of a defaulted loan pays outstanding principal, in one or more payments, and it is credited to lenders
+    fn process_pay_defaulted_loan_as_guarantor(
+        accounts: &[AccountInfo],
+        amount_to_pay_input: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let guarantor_main_account = next_account_info(account_info_iter)?;
+
+        if !guarantor_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let guarantor_dassi_coin_account_to_debit = next_account_info(account_info_iter)?;
+        // dassi_coin_vault_account is program controlled vault account and can be only controlled by our deployed program for debit funds or any kind of operation
+        let dassi_coin_vault_account = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+
+        if loan_info_header_data.guarantor_main_acc_pubkey != *guarantor_main_account.key {
+            return Err(DassiError::GuarantorAccountMismatched.into());
+        }
+
+        // outstanding principal is paid in mint of the loan
+        validation::validate_user_token_account(
+            guarantor_dassi_coin_account_to_debit,
+            guarantor_main_account.key,
+            &loan_info_header_data.loan_mint,
+        )?;
+        let dassi_coin_vault_account_data_before = validation::validate_mint_vault(
+            dassi_coin_vault_account,
+            &loan_info_header_data.loan_mint,
+            program_id,
+        )?;
+
+        if Self::get_loan_status(&loan_info_header_data)? != LoanStatus::Defaulted {
+            return Err(DassiError::LoanNotDefaulted.into());
+        }
+
+        let outstanding_principal = loan_info_header_data
+            .total_loan_amount
+            .checked_sub(loan_info_header_data.principal_repaid_by_borrower)
+            .unwrap()
+            .checked_sub(loan_info_header_data.guarantor_repaid_amount)
+            .unwrap();
+
+        if outstanding_principal == 0 {
+            return Err(DassiError::LoanAlreadyPaid.into());
+        }
+
+        if amount_to_pay_input == 0 || amount_to_pay_input > outstanding_principal {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        let dassi_coin_vault_balance_before = dassi_coin_vault_account_data_before.amount;
+
+        let transfer_outstanding_amount_to_vault_ix = spl_token::instruction::transfer(
+            token_program.key,
+            guarantor_dassi_coin_account_to_debit.key,
+            dassi_coin_vault_account.key,
+            guarantor_main_account.key,
+            &[],
+            amount_to_pay_input,
+        )?;
+
+        msg!("Calling the token program to transfer outstanding loan amount to vault...");
+        msg!(
+            "amount of tokens to transfer {}, guarantor debit key {}",
+            spl_token::amount_to_ui_amount(amount_to_pay_input, loan_info_header_data.loan_mint_decimals),
+            guarantor_dassi_coin_account_to_debit.key.to_string()
+        );
+
+        invoke(
+            &transfer_outstanding_amount_to_vault_ix,
+            &[
+                guarantor_dassi_coin_account_to_debit.clone(),
+                dassi_coin_vault_account.clone(),
+                guarantor_main_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let dassi_coin_vault_account_data_after =
+            TokenAccount::unpack(&dassi_coin_vault_account.data.borrow())?;
+        let vault_balance_increased = dassi_coin_vault_account_data_after
+            .amount
+            .checked_sub(dassi_coin_vault_balance_before)
+            .unwrap();
+
+        if vault_balance_increased != amount_to_pay_input {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        loan_info_header_data.guarantor_repaid_amount = loan_info_header_data
+            .guarantor_repaid_amount
+            .checked_add(vault_balance_increased)
+            .unwrap();
+
+        Self::add_pending_lenders_credit(&mut loan_info_header_data, vault_balance_increased, 0u64);
+
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+
+        Ok(())
+    }
+
+    // Creates stake account of guarantor in an accepted loan mint and its stake vault, a token account of that mint at a pda of
+    // stake account. Vault owner is the "DassiFinance" pda, same as mint vaults, so that slashed stake can be moved to vault of
+    // loan mint by program. Stake backing a loan is locked in the loan mint, so a guarantor needs a stake account per loan mint
+    fn process_initialize_guarantor_stake_vault(
+        accounts: &[AccountInfo],
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let guarantor_main_account = next_account_info(account_info_iter)?;
+
+        if !guarantor_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let guarantor_storage_account = next_account_info(account_info_iter)?;
+        validation::check_guarantor_storage_account(
+            guarantor_storage_account,
+            guarantor_main_account.key,
+            program_id,
+        )?;
+
+        let guarantor_data =
+            GuarantorAccount::unpack(&guarantor_storage_account.data.try_borrow().unwrap())?;
+        if guarantor_data.acc_type != AccTypes::GuarantorAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if guarantor_data.guarantor_main_acc_pubkey != *guarantor_main_account.key {
+            return Err(DassiError::GuarantorAccountMismatched.into());
+        }
+
+        let guarantor_stake_account = next_account_info(account_info_iter)?;
+        let guarantor_stake_vault_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        validation::unpack_loan_mint(mint_account, config)?;
+
+        let (expected_guarantor_stake_pubkey, stake_bump_seed) =
+            utils::get_guarantor_stake_address(guarantor_main_account.key, mint_account.key, program_id);
+        if expected_guarantor_stake_pubkey != *guarantor_stake_account.key {
+            return Err(DassiError::AccountMismatched.into());
+        }
+        if guarantor_stake_account.data_len() != 0 {
+            return Err(DassiError::GuarantorStakeAccountAlreadyInitialized.into());
+        }
+
+        let (expected_guarantor_stake_vault_pubkey, stake_vault_bump_seed) =
+            utils::get_guarantor_stake_vault_address(guarantor_stake_account.key, program_id);
+        if expected_guarantor_stake_vault_pubkey != *guarantor_stake_vault_account.key {
+            return Err(DassiError::GuarantorStakeVaultDoesNotMatched.into());
+        }
+
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        if pda != *pda_account.key {
+            return Err(DassiError::PdaAccountDoesNotMatched.into());
+        }
+
+        let system_program = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let rent_sysvar_account = next_account_info(account_info_iter)?;
+
+        msg!("Calling the system program to create guarantor stake account...");
+        Self::create_program_account(
+            guarantor_main_account,
+            guarantor_stake_account,
+            system_program,
+            GuarantorStakeAccount::LEN,
+            &[
+                &b"DassiFinanceGuarantorStake"[..],
+                guarantor_main_account.key.as_ref(),
+                mint_account.key.as_ref(),
+                &[stake_bump_seed],
+            ],
+            program_id,
+        )?;
+
+        let mut guarantor_stake_data =
+            GuarantorStakeAccount::unpack_unchecked(&guarantor_stake_account.data.borrow())?;
+
+        guarantor_stake_data.is_initialized = true;
+        guarantor_stake_data.acc_type = AccTypes::GuarantorStakeAcc as u8;
+        guarantor_stake_data.guarantor_main_acc_pubkey = *guarantor_main_account.key;
+        guarantor_stake_data.mint = *mint_account.key;
+
+        GuarantorStakeAccount::pack(
+            guarantor_stake_data,
+            &mut guarantor_stake_account.data.borrow_mut(),
+        )?;
+
+        msg!("Calling the system program to create guarantor stake vault...");
+        Self::create_program_account(
+            guarantor_main_account,
+            guarantor_stake_vault_account,
+            system_program,
+            TokenAccount::LEN,
+            &[
+                &b"DassiFinanceGuarantorStakeVault"[..],
+                guarantor_stake_account.key.as_ref(),
+                &[stake_vault_bump_seed],
+            ],
+            token_program.key,
+        )?;
+
+        let initialize_stake_vault_ix = spl_token::instruction::initialize_account(
+            token_program.key,
+            guarantor_stake_vault_account.key,
+            mint_account.key,
+            &pda,
+        )?;
+        msg!("Calling the token program to initialize guarantor stake vault...");
+        invoke(
+            &initialize_stake_vault_ix,
+            &[
+                guarantor_stake_vault_account.clone(),
+                mint_account.clone(),
+                pda_account.clone(),
+                rent_sysvar_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // Every accepted loan mint has its own vault, which holds lent funds and repayments of loans in that mint
+    fn process_initialize_mint_vault(
+        accounts: &[AccountInfo],
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        let mint_vault_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        validation::unpack_loan_mint(mint_account, config)?;
+
+        let (expected_mint_vault_pubkey, mint_vault_bump_seed) =
+            utils::get_mint_vault_address(mint_account.key, program_id);
+
+        if expected_mint_vault_pubkey != *mint_vault_account.key {
+            return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+        }
+
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        if pda != *pda_account.key {
+            return Err(DassiError::PdaAccountDoesNotMatched.into());
+        }
+
+        let system_program = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let rent_sysvar_account = next_account_info(account_info_iter)?;
+
+        msg!("Calling the system program to create mint vault...");
+        Self::create_program_account(
+            admin_account,
+            mint_vault_account,
+            system_program,
+            TokenAccount::LEN,
+            &[
+                &b"DassiFinanceVault"[..],
+                mint_account.key.as_ref(),
+                &[mint_vault_bump_seed],
+            ],
+            token_program.key,
+        )?;
+
+        let initialize_mint_vault_ix = spl_token::instruction::initialize_account(
+            token_program.key,
+            mint_vault_account.key,
+            mint_account.key,
+            &pda,
+        )?;
+        msg!("Calling the token program to initialize mint vault...");
+        invoke(
+            &initialize_mint_vault_ix,
+            &[
+                mint_vault_account.clone(),
+                mint_account.clone(),
+                pda_account.clone(),
+                rent_sysvar_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // Moves whole balance of deployed DassiCoin vault to vault of DassiCoin mint, which every fund path uses now. Lender
+    // balances migrated from legacy lenders storage are paid out of the mint vault, so MigrateLenderAccount waits for this
+    fn process_migrate_legacy_vault(
+        accounts: &[AccountInfo],
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        if config.legacy_vault_migrated {
+            return Err(DassiError::LegacyVaultAlreadyMigrated.into());
+        }
+
+        let legacy_vault_account = next_account_info(account_info_iter)?;
+        let legacy_vault_data =
+            validation::validate_legacy_vault(legacy_vault_account, &config, program_id)?;
+
+        let mint_vault_account = next_account_info(account_info_iter)?;
+        let mint_vault_data_before = validation::validate_mint_vault(
+            mint_vault_account,
+            &config.params.accepted_mint,
+            program_id,
+        )?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let (pda, bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        if pda != *pda_account.key {
+            return Err(DassiError::PdaAccountDoesNotMatched.into());
+        }
+
+        let amount_to_migrate = legacy_vault_data.amount;
+
+        let transfer_legacy_vault_balance_ix = spl_token::instruction::transfer(
+            token_program.key,
+            legacy_vault_account.key,
+            mint_vault_account.key,
+            &pda,
+            &[&pda],
+            amount_to_migrate,
+        )?;
+        msg!("Calling the token program to transfer legacy vault balance to mint vault...");
+        msg!("amount of tokens to transfer {}", amount_to_migrate);
+        invoke_signed(
+            &transfer_legacy_vault_balance_ix,
+            &[
+                legacy_vault_account.clone(),
+                mint_vault_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"DassiFinance"[..], &[bump_seed]]],
+        )?;
+
+        let mint_vault_balance_after = TokenAccount::unpack(&mint_vault_account.data.borrow())?.amount;
+        let mint_vault_balance_increased = mint_vault_balance_after
+            .checked_sub(mint_vault_data_before.amount)
+            .unwrap();
+
+        if mint_vault_balance_increased != amount_to_migrate {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        config.legacy_vault_migrated = true;
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    // Stake is held in stake vault of guarantor stake account, in mint of that account
+    fn process_stake_guarantor_collateral(
+        accounts: &[AccountInfo],
+        amount_to_stake_input: u64,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let guarantor_main_account = next_account_info(account_info_iter)?;
+
+        if !guarantor_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let guarantor_token_account_to_debit = next_account_info(account_info_iter)?;
+
+        let guarantor_stake_vault_account = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let guarantor_stake_account = next_account_info(account_info_iter)?;
+
+        let mint_account = next_account_info(account_info_iter)?;
+        let mint_data = validation::unpack_loan_mint(mint_account, config)?;
+
+        let mut guarantor_stake_data = Self::load_guarantor_stake_account(
+            guarantor_stake_account,
+            guarantor_main_account.key,
+            mint_account.key,
+            program_id,
+        )?;
+
+        validation::validate_user_token_account(
+            guarantor_token_account_to_debit,
+            guarantor_main_account.key,
+            mint_account.key,
+        )?;
+
+        let guarantor_stake_vault_balance_before = validation::validate_guarantor_stake_vault(
+            guarantor_stake_vault_account,
+            guarantor_stake_account.key,
+            mint_account.key,
+            program_id,
+        )?
+        .amount;
+
+        if amount_to_stake_input == 0 {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        let transfer_stake_to_vault_ix = spl_token::instruction::transfer(
+            token_program.key,
+            guarantor_token_account_to_debit.key,
+            guarantor_stake_vault_account.key,
+            guarantor_main_account.key,
+            &[],
+            amount_to_stake_input,
+        )?;
+        msg!("Calling the token program to transfer guarantor stake to stake vault...");
+        msg!(
+            "amount of tokens to transfer {}, guarantor debit key {}",
+            spl_token::amount_to_ui_amount(amount_to_stake_input, mint_data.decimals),
+            guarantor_token_account_to_debit.key.to_string()
+        );
+        invoke(
+            &transfer_stake_to_vault_ix,
+            &[
+                guarantor_token_account_to_debit.clone(),
+                guarantor_stake_vault_account.clone(),
+                guarantor_main_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let guarantor_stake_vault_balance_after =
+            TokenAccount::unpack(&guarantor_stake_vault_account.data.borrow())?.amount;
+        let stake_vault_balance_increased = guarantor_stake_vault_balance_after
+            .checked_sub(guarantor_stake_vault_balance_before)
+            .unwrap();
+
+        if stake_vault_balance_increased != amount_to_stake_input {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        guarantor_stake_data.staked_amount = guarantor_stake_data
+            .staked_amount
+            .checked_add(stake_vault_balance_increased)
+            .unwrap();
+        msg!("guarantor staked amount {}", guarantor_stake_data.staked_amount);
+
+        GuarantorStakeAccount::pack(
+            guarantor_stake_data,
+            &mut guarantor_stake_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        Ok(())
+    }
+
+    // Guarantor can take back only stake which is not locked for any active loan in mint of stake account
+    fn process_unstake_guarantor_collateral(
+        accounts: &[AccountInfo],
+        amount_to_unstake_input: u64,
+        config: &ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let guarantor_main_account = next_account_info(account_info_iter)?;
+
+        if !guarantor_main_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let guarantor_token_account_to_credit = next_account_info(account_info_iter)?;
+
+        let guarantor_stake_vault_account = next_account_info(account_info_iter)?;
+
+        let token_program = next_account_info(account_info_iter)?;
+        validation::check_token_program(token_program)?;
+
+        let guarantor_stake_account = next_account_info(account_info_iter)?;
+
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let (pda, bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+        if pda != *pda_account.key {
+            return Err(DassiError::PdaAccountDoesNotMatched.into());
+        }
+
+        let mint_account = next_account_info(account_info_iter)?;
+        let mint_data = validation::unpack_loan_mint(mint_account, config)?;
+
+        let mut guarantor_stake_data = Self::load_guarantor_stake_account(
+            guarantor_stake_account,
+            guarantor_main_account.key,
+            mint_account.key,
+            program_id,
+        )?;
+
+        validation::validate_user_token_account(
+            guarantor_token_account_to_credit,
+            guarantor_main_account.key,
+            mint_account.key,
+        )?;
+
+        let guarantor_stake_vault_balance_before = validation::validate_guarantor_stake_vault(
+            guarantor_stake_vault_account,
+            guarantor_stake_account.key,
+            mint_account.key,
+            program_id,
+        )?
+        .amount;
+
+        let free_stake_amount = guarantor_stake_data
+            .staked_amount
+            .checked_sub(guarantor_stake_data.locked_stake_amount)
+            .unwrap();
+
+        if amount_to_unstake_input == 0 {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        if amount_to_unstake_input > free_stake_amount {
+            return Err(DassiError::InsufficientGuarantorStake.into());
+        }
+
+        let transfer_stake_to_guarantor_ix = spl_token::instruction::transfer(
+            token_program.key,
+            guarantor_stake_vault_account.key,
+            guarantor_token_account_to_credit.key,
+            &pda,
+            &[&pda],
+            amount_to_unstake_input,
+        )?;
+        msg!("Calling the token program to transfer unstaked amount to guarantor...");
+        msg!(
+            "amount of tokens to transfer {}, guarantor credit key {}",
+            spl_token::amount_to_ui_amount(amount_to_unstake_input, mint_data.decimals),
+            guarantor_token_account_to_credit.key.to_string()
+        );
+        invoke_signed(
+            &transfer_stake_to_guarantor_ix,
+            &[
+                guarantor_stake_vault_account.clone(),
+                guarantor_token_account_to_credit.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"DassiFinance"[..], &[bump_seed]]],
+        )?;
+
+        let guarantor_stake_vault_balance_after =
+            TokenAccount::unpack(&guarantor_stake_vault_account.data.borrow())?.amount;
+        let stake_vault_balance_decreased = guarantor_stake_vault_balance_before
+            .checked_sub(guarantor_stake_vault_balance_after)
+            .unwrap();
+
+        if stake_vault_balance_decreased != amount_to_unstake_input {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        guarantor_stake_data.staked_amount = guarantor_stake_data
+            .staked_amount
+            .checked_sub(stake_vault_balance_decreased)
+            .unwrap();
+        msg!("guarantor staked amount {}", guarantor_stake_data.staked_amount);
+
+        GuarantorStakeAccount::pack(
+            guarantor_stake_data,
+            &mut guarantor_stake_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        Ok(())
+    }
+
+    // Creates protocol config pda, only admin key compiled into the program can do it. It becomes admin of the config and
+    // holds every other role too until admin hands them out with SetRoles. Compiled in key is checked only here, afterwards
+    // admin is the one stored in config
+    fn process_initialize_config(
+        accounts: &[AccountInfo],
+        params_input: ProtocolConfigParams,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if *admin_account.key != utils::get_admin_pubkey() {
+            return Err(DassiError::AdminDoesNotMatched.into());
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+        let (expected_config_pubkey, config_bump_seed) =
+            utils::get_protocol_config_address(program_id);
+
+        if expected_config_pubkey != *config_account.key {
+            return Err(DassiError::InvalidConfigAccount.into());
+        }
+
+        if config_account.data_len() != 0 {
+            return Err(DassiError::ConfigAlreadyInitialized.into());
+        }
+
+        let system_program = next_account_info(account_info_iter)?;
+
+        Self::validate_protocol_config_params(&params_input)?;
+
+        msg!("Calling the system program to create protocol config account...");
+        Self::create_program_account(
+            admin_account,
+            config_account,
+            system_program,
+            ProtocolConfig::LEN,
+            &[&b"DassiFinanceConfig"[..], &[config_bump_seed]],
+            program_id,
+        )?;
+
+        let config = ProtocolConfig {
+            is_initialized: true,
+            acc_type: AccTypes::ConfigAcc as u8,
+            admin: *admin_account.key,
+            pending_admin: Pubkey::default(),
+            pauser: *admin_account.key,
+            risk_manager: *admin_account.key,
+            paused_operations: 0,
+            legacy_vault_migrated: false,
+            params: params_input,
+        };
+        ProtocolConfig::pack(config, &mut config_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_update_config(
+        accounts: &[AccountInfo],
+        params_input: ProtocolConfigParams,
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let risk_manager_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(
+            risk_manager_account,
+            &config.risk_manager,
+            DassiError::RiskManagerDoesNotMatched,
+        )?;
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        Self::validate_protocol_config_params(&params_input)?;
+
+        // risk manager only tunes risk parameters, loans, vaults and stakes are bound to the mints
+        if params_input.accepted_mint != config.params.accepted_mint
+            || params_input.accepted_loan_mints != config.params.accepted_loan_mints
+        {
+            return Err(DassiError::ConfigMintsCanNotBeUpdated.into());
+        }
+
+        msg!("updating protocol config {:?}", params_input);
+        config.params = params_input;
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    fn process_add_accepted_loan_mint(
+        accounts: &[AccountInfo],
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        let mint_account = next_account_info(account_info_iter)?;
+        validation::unpack_new_loan_mint(mint_account, &config)?;
+
+        let free_slot = config
+            .params
+            .accepted_loan_mints
+            .iter_mut()
+            .find(|accepted_loan_mint| **accepted_loan_mint == Pubkey::default())
+            .ok_or(DassiError::AcceptedLoanMintsFull)?;
+        *free_slot = *mint_account.key;
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        msg!("added {} to accepted loan mints", mint_account.key);
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    fn process_propose_admin(
+        accounts: &[AccountInfo],
+        new_admin_input: Pubkey,
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        msg!("proposing {} as new admin", new_admin_input);
+        config.pending_admin = new_admin_input;
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    fn process_accept_admin(
+        accounts: &[AccountInfo],
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pending_admin_account = next_account_info(account_info_iter)?;
+
+        // nobody can sign for default pubkey, so this also fails when no handover is pending
+        Self::check_role(
+            pending_admin_account,
+            &config.pending_admin,
+            DassiError::PendingAdminDoesNotMatched,
+        )?;
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        msg!("admin changed from {} to {}", config.admin, config.pending_admin);
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    fn process_set_roles(
+        accounts: &[AccountInfo],
+        pauser_input: Pubkey,
+        risk_manager_input: Pubkey,
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        msg!("pauser: {}, risk manager: {}", pauser_input, risk_manager_input);
+        config.pauser = pauser_input;
+        config.risk_manager = risk_manager_input;
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    // Payment is matched against the next unpaid emi of the schedule. If it is more than what is left of that emi, rest
+    // of it moves on to following emis, if it is less the emi stays partially paid. Within every emi interest is paid first.
+    // Payments towards emis overdue after grace period also pay late fee, and borrower credit score changes whenever an emi
+    // gets fully paid. Returns principal, interest and late fee parts of whole payment
+    fn apply_payment_to_emi_schedule(
+        loan_info_data_byte_array: &mut [u8],
+        loan_info_header_data: &mut LoanInfoAccDataHeader,
+        borrower_credit_score: &mut u64,
+        payment_amount: u64,
+        now: u64,
+        config: &ProtocolConfig,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let loan_terms = Self::get_loan_terms(loan_info_header_data)?;
+        let emi_amount = loan_terms
+            .calculate_emi_amount()
+            .ok_or(DassiError::AmountOverflow)?;
+        let mut amount_left_to_apply = payment_amount;
+        let mut total_principal_amount: u64 = 0;
+        let mut total_interest_amount: u64 = 0;
+        let mut total_late_fee_amount: u64 = 0;
+
+        while amount_left_to_apply > 0 {
+            let emi_index = loan_info_header_data.next_index_to_store_repayment_info;
+            if emi_index >= loan_info_header_data.number_of_emis_needed_to_repay_the_loan {
+                // borrower can't pay more than he owes
+                return Err(DassiError::ExpectedAmountMismatch.into());
+            }
+
+            let loan_info_repayment_data_si = state::LOAN_INFO_ACC_REPAYMENT_DATA_SI
+                + (emi_index as usize) * state::LOAN_INFO_ACC_REPAYMENT_DATA_BYTES;
+            let loan_info_repayment_data_ei =
+                loan_info_repayment_data_si + state::LOAN_INFO_ACC_REPAYMENT_DATA_BYTES;
+            let mut repayment_data = state::unpack_to_loan_info_acc_repayment_data(
+                &loan_info_data_byte_array[loan_info_repayment_data_si..loan_info_repayment_data_ei],
+            )
+            .unwrap();
+
+            // interest of this emi is calculated from loan state before anything was paid towards it
+            let emi_principal_paid = repayment_data
+                .emi_repayment_amount
+                .checked_sub(repayment_data.emi_interest_amount)
+                .unwrap();
+            let principal_repaid_before_emi = loan_info_header_data
+                .principal_repaid_by_borrower
+                .checked_sub(emi_principal_paid)
+                .unwrap();
+            let interest_paid_before_emi = loan_info_header_data
+                .interest_paid_by_borrower
+                .checked_sub(repayment_data.emi_interest_amount)
+                .unwrap();
+            let emi_interest_due = loan_terms
+                .calculate_interest_due(
+                    principal_repaid_before_emi,
+                    interest_paid_before_emi,
+                    emi_index,
+                )
+                .ok_or(DassiError::AmountOverflow)?;
+            let outstanding_principal_before_emi = loan_info_header_data
+                .total_loan_amount
+                .checked_sub(principal_repaid_before_emi)
+                .unwrap();
+            let amount_due_before_emi = outstanding_principal_before_emi
+                .checked_add(emi_interest_due)
+                .ok_or(DassiError::AmountOverflow)?;
+
+            // last emi clears whatever is left so rounding never leaves loan unpaid
+            let expected_emi_amount = if emi_index + 1
+                == loan_info_header_data.number_of_emis_needed_to_repay_the_loan
+            {
+                amount_due_before_emi
+            } else {
+                emi_amount.min(amount_due_before_emi)
+            };
+
+            let emi_due_timestamp = amortization::calculate_emi_due_timestamp(
+                loan_info_header_data.first_repayment_last_date_timestamp,
+                loan_info_header_data.emi_interval_days as u64,
+                emi_index,
+            )
+            .ok_or(DassiError::AmountOverflow)?;
+            let days_late = credit_score::calculate_days_late(
+                emi_due_timestamp,
+                config.params.late_payment_grace_period_days as u64,
+                now,
+            );
+
+            let amount_left_in_emi = expected_emi_amount
+                .checked_sub(repayment_data.emi_repayment_amount)
+                .unwrap();
+            let (amount_to_apply, late_fee_amount) = match days_late {
+                Some(_) => credit_score::split_overdue_payment(
+                    amount_left_to_apply,
+                    amount_left_in_emi,
+                    config.params.late_fee_bps,
+                )
+                .ok_or(DassiError::AmountOverflow)?,
+                None => (amount_left_to_apply.min(amount_left_in_emi), 0u64),
+            };
+            let (principal_amount, interest_amount) = amortization::split_emi_payment(
+                amount_to_apply,
+                emi_interest_due
+                    .checked_sub(repayment_data.emi_interest_amount)
+                    .unwrap(),
+                outstanding_principal_before_emi
+                    .checked_sub(emi_principal_paid)
+                    .unwrap(),
+            )
+            .ok_or(DassiError::ExpectedAmountMismatch)?;
+
+            repayment_data.emi_repayment_timestamp = now;
+            repayment_data.emi_repayment_amount = repayment_data
+                .emi_repayment_amount
+                .checked_add(amount_to_apply)
+                .unwrap();
+            repayment_data.emi_interest_amount = repayment_data
+                .emi_interest_amount
+                .checked_add(interest_amount)
+                .unwrap();
+
+            if repayment_data.emi_repayment_amount >= expected_emi_amount {
+                match days_late {
+                    None => {
+                        repayment_data.emi_status = EmiStatus::PaidOnTime as u8;
+                        loan_info_header_data.number_of_emis_paid_on_time = loan_info_header_data
+                            .number_of_emis_paid_on_time
+                            .checked_add(1)
+                            .unwrap();
+                        *borrower_credit_score =
+                            credit_score::calculate_score_after_on_time_emi(*borrower_credit_score);
+                    }
+                    Some(days_late) => {
+                        repayment_data.emi_status = EmiStatus::PaidLate as u8;
+                        loan_info_header_data.number_of_emis_paid_late = loan_info_header_data
+                            .number_of_emis_paid_late
+                            .checked_add(1)
+                            .unwrap();
+                        *borrower_credit_score = credit_score::calculate_score_after_late_emi(
+                            *borrower_credit_score,
+                            days_late,
+                        );
+                    }
+                }
+                loan_info_header_data.next_index_to_store_repayment_info =
+                    emi_index.checked_add(1).unwrap();
+            } else {
+                repayment_data.emi_status = EmiStatus::PartiallyPaid as u8;
+            }
+            msg!("emi {} status {}", emi_index, repayment_data.emi_status);
+
+            state::pack_to_loan_info_acc_repayment_data(
+                repayment_data,
+                &mut loan_info_data_byte_array
+                    [loan_info_repayment_data_si..loan_info_repayment_data_ei],
+            )
+            .unwrap();
+
+            loan_info_header_data.principal_repaid_by_borrower = loan_info_header_data
+                .principal_repaid_by_borrower
+                .checked_add(principal_amount)
+                .unwrap();
+            loan_info_header_data.interest_paid_by_borrower = loan_info_header_data
+                .interest_paid_by_borrower
+                .checked_add(interest_amount)
+                .unwrap();
+            total_principal_amount = total_principal_amount.checked_add(principal_amount).unwrap();
+            loan_info_header_data.late_fees_paid_by_borrower = loan_info_header_data
+                .late_fees_paid_by_borrower
+                .checked_add(late_fee_amount)
+                .unwrap();
+            total_interest_amount = total_interest_amount.checked_add(interest_amount).unwrap();
+            total_late_fee_amount = total_late_fee_amount.checked_add(late_fee_amount).unwrap();
+            amount_left_to_apply = amount_left_to_apply
+                .checked_sub(amount_to_apply.checked_add(late_fee_amount).unwrap())
+                .unwrap();
+        }
+
+        Ok((
+            total_principal_amount,
+            total_interest_amount,
+            total_late_fee_amount,
+        ))
+    }
+
+    // Amount paid towards a loan waits in loan account until CreditRepaymentsToLenders credits it to lender accounts.
+    // earning_amount is the part of amount_to_credit which is interest or fees
+    fn add_pending_lenders_credit(
+        loan_info_header_data: &mut LoanInfoAccDataHeader,
+        amount_to_credit: u64,
+        earning_amount: u64,
+    ) {
+        loan_info_header_data.lenders_pending_credit_amount = loan_info_header_data
+            .lenders_pending_credit_amount
+            .checked_add(amount_to_credit)
+            .unwrap();
+        loan_info_header_data.lenders_pending_earning_amount = loan_info_header_data
+            .lenders_pending_earning_amount
+            .checked_add(earning_amount)
+            .unwrap();
+    }
+
+    // Credits repayments of a loan to free wallets of its lenders in proportion to their lent amounts. Shares of a round are
+    // computed again on every call from lent amounts, which don't change after fundraising, so every call of a round credits
+    // same shares and dust index moves only when round ends
+    fn process_credit_repayments_to_lenders(
+        accounts: &[AccountInfo],
+        num_accounts_input: u16,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let fee_payer_account = next_account_info(account_info_iter)?;
+
+        if !fee_payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if num_accounts_input == 0 {
+            return Err(DassiError::InvalidInstruction.into());
+        }
+
+        let loan_info_storage_account = next_account_info(account_info_iter)?;
+
+        if loan_info_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+
+        // start a new round with everything paid since last round started
+        if loan_info_header_data.lenders_round_credit_amount == 0 {
+            if loan_info_header_data.lenders_pending_credit_amount == 0 {
+                return Err(DassiError::NoRepaymentsToCredit.into());
+            }
+            loan_info_header_data.lenders_round_credit_amount =
+                loan_info_header_data.lenders_pending_credit_amount;
+            loan_info_header_data.lenders_round_earning_amount =
+                loan_info_header_data.lenders_pending_earning_amount;
+            loan_info_header_data.lenders_pending_credit_amount = 0u64;
+            loan_info_header_data.lenders_pending_earning_amount = 0u64;
+            loan_info_header_data.next_index_to_credit = 0u8;
+        }
+
+        // every lender gets part of amount in proportion to his lent amount
+        let mut lent_amounts: Vec<u64> =
+            Vec::with_capacity(loan_info_header_data.next_index_to_store_lender_data as usize);
+        for i in 0..loan_info_header_data.next_index_to_store_lender_data {
+            let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+                + (i as usize) * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data_ei: usize =
+                loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data: LoanInfoAccLendersData =
+                state::unpack_to_loan_info_acc_lender_data(
+                    &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+                )
+                .unwrap();
+            lent_amounts.push(loan_info_lender_data.lent_amount);
+        }
+
+        let amount_to_credit = loan_info_header_data.lenders_round_credit_amount;
+        let earning_amount = loan_info_header_data.lenders_round_earning_amount;
+        let (amount_shares, next_index_to_receive_dust) = utils::distribute_pro_rata(
+            amount_to_credit,
+            &lent_amounts,
+            loan_info_header_data.next_index_to_receive_dust as usize,
+        )
+        .ok_or(DassiError::AmountOverflow)?;
+
+        let last_index_to_credit: u8 = (loan_info_header_data.next_index_to_credit as u16)
+            .checked_add(num_accounts_input)
+            .unwrap()
+            .min(loan_info_header_data.next_index_to_store_lender_data as u16)
+            as u8;
+
+        for i in loan_info_header_data.next_index_to_credit..last_index_to_credit {
+            let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+                + (i as usize) * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data_ei: usize =
+                loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+            let loan_info_lender_data: LoanInfoAccLendersData =
+                state::unpack_to_loan_info_acc_lender_data(
+                    &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+                )
+                .unwrap();
+            let amount_share: u64 = amount_shares[i as usize];
+            // lender's part of earnings is in same proportion as whole amount
+            let earning_share: u64 = (amount_share as u128)
+                .checked_mul(earning_amount as u128)
+                .unwrap()
+                .checked_div(amount_to_credit as u128)
+                .unwrap() as u64;
+
+            // lender accounts are passed in same order as lenders are stored in loan account
+            let lender_storage_account = next_account_info(account_info_iter)?;
+            let mut lender_data = Self::load_lender_account(
+                lender_storage_account,
+                &loan_info_lender_data.lender_main_acc_pubkey,
+                program_id,
+            )?;
+            if lender_data.mint != loan_info_header_data.loan_mint {
+                return Err(DassiError::MintDoesNotMatched.into());
+            }
+
+            lender_data.total_lending_amount = lender_data
+                .total_lending_amount
+                .checked_add(amount_share as u128)
+                .unwrap();
+            lender_data.total_unique_lending_amount = lender_data
+                .total_unique_lending_amount
+                .checked_add(amount_share)
+                .unwrap();
+            lender_data.amount_to_withdraw_or_lend = lender_data
+                .amount_to_withdraw_or_lend
+                .checked_add(amount_share)
+                .unwrap();
+            lender_data.total_interest_earned = lender_data
+                .total_interest_earned
+                .checked_add(earning_share)
+                .unwrap();
+
+            LenderAccount::pack(
+                lender_data,
+                &mut lender_storage_account.data.try_borrow_mut().unwrap(),
+            )?;
+
+            let lender_position_account = next_account_info(account_info_iter)?;
+            let mut lender_position_data = Self::load_lender_position_account(
+                lender_position_account,
+                &loan_info_lender_data.lender_main_acc_pubkey,
+                loan_info_storage_account.key,
+                program_id,
+            )?;
+            lender_position_data.principal_returned = lender_position_data
+                .principal_returned
+                .checked_add(amount_share.checked_sub(earning_share).unwrap())
+                .unwrap();
+            lender_position_data.interest_earned = lender_position_data
+                .interest_earned
+                .checked_add(earning_share)
+                .unwrap();
+            if lender_position_data.principal_returned >= lender_position_data.principal_lent {
+                lender_position_data.position_status = LenderPositionStatus::Repaid as u8;
+            }
+            LenderPositionAccount::pack(
+                lender_position_data,
+                &mut lender_position_account.data.try_borrow_mut().unwrap(),
+            )?;
+        }
+
+        loan_info_header_data.next_index_to_credit = last_index_to_credit;
+
+        if loan_info_header_data.next_index_to_credit
+            >= loan_info_header_data.next_index_to_store_lender_data
+        {
+            msg!(
+                "credited {} tokens of mint {} to lenders",
+                spl_token::amount_to_ui_amount(
+                    loan_info_header_data.lenders_round_credit_amount,
+                    loan_info_header_data.loan_mint_decimals
+                ),
+                loan_info_header_data.loan_mint
+            );
+            loan_info_header_data.next_index_to_receive_dust = next_index_to_receive_dust as u8;
+            loan_info_header_data.lenders_round_credit_amount = 0u64;
+            loan_info_header_data.lenders_round_earning_amount = 0u64;
+            loan_info_header_data.next_index_to_credit = 0u8;
+        }
+
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+
+        Ok(())
+    }
+
+    // Lender account of lender_main_pubkey, it must be created with InitializeLenderAccount or MigrateLenderAccount first. Its
+    // address is derived from the mint stored in it, callers check the mint matches coins they move
+    fn load_lender_account(
+        lender_storage_account: &AccountInfo,
+        lender_main_pubkey: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<LenderAccount, ProgramError> {
+        if lender_storage_account.owner != program_id {
+            return Err(DassiError::WrongAccountPassed.into());
+        }
+        let lender_data = LenderAccount::unpack(&lender_storage_account.data.try_borrow().unwrap())?;
+        validation::check_lender_storage_account(
+            lender_storage_account,
+            lender_main_pubkey,
+            &lender_data.mint,
+            program_id,
+        )?;
+        if lender_data.acc_type != AccTypes::LenderAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if lender_data.lender_main_acc_pubkey != *lender_main_pubkey {
+            return Err(DassiError::LenderAccountMismatched.into());
+        }
+        Ok(lender_data)
+    }
+
+    // stake account of guarantor in mint, its address is derived from both so a stake in another mint can't be passed
+    fn load_guarantor_stake_account(
+        guarantor_stake_account: &AccountInfo,
+        guarantor_main_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<GuarantorStakeAccount, ProgramError> {
+        validation::check_guarantor_stake_account(
+            guarantor_stake_account,
+            guarantor_main_pubkey,
+            mint_pubkey,
+            program_id,
+        )?;
+        let guarantor_stake_data =
+            GuarantorStakeAccount::unpack(&guarantor_stake_account.data.try_borrow().unwrap())?;
+        if guarantor_stake_data.acc_type != AccTypes::GuarantorStakeAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if guarantor_stake_data.guarantor_main_acc_pubkey != *guarantor_main_pubkey {
+            return Err(DassiError::GuarantorAccountMismatched.into());
+        }
+        if guarantor_stake_data.mint != *mint_pubkey {
+            return Err(DassiError::GuarantorStakeNotInLoanMint.into());
+        }
+        Ok(guarantor_stake_data)
+    }
+
+    fn load_lender_position_account(
+        lender_position_account: &AccountInfo,
+        lender_main_pubkey: &Pubkey,
+        loan_info_pubkey: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<LenderPositionAccount, ProgramError> {
+        validation::check_lender_position_account(
+            lender_position_account,
+            lender_main_pubkey,
+            loan_info_pubkey,
+            program_id,
+        )?;
+        let lender_position_data =
+            LenderPositionAccount::unpack(&lender_position_account.data.try_borrow().unwrap())?;
+        if lender_position_data.acc_type != AccTypes::LenderPositionAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+        if lender_position_data.lender_main_acc_pubkey != *lender_main_pubkey
+            || lender_position_data.loan_info_acc_pubkey != *loan_info_pubkey
+        {
+            return Err(DassiError::LenderAccountMismatched.into());
+        }
+        Ok(lender_position_data)
+    }
+
+    // Amount lender is charged for lending amount_to_lend_input in a loan. Loan can't be over funded, so lender is charged only
+    // what loan still needs
+    fn get_amount_to_lend(
+        loan_info_header_data: &LoanInfoAccDataHeader,
+        amount_to_lend_input: u64,
+        config: &ProtocolConfig,
+    ) -> Result<u64, ProgramError> {
+        let lending_amount_unit =
+            Self::get_lending_amount_unit(loan_info_header_data.loan_mint_decimals)?;
+        if amount_to_lend_input == 0 || amount_to_lend_input.checked_rem(lending_amount_unit) != Some(0) {
+            return Err(DassiError::LendingAmountNotMultipleOfUnit.into());
+        }
+
+        let remaining_loan_amount = loan_info_header_data
+            .total_loan_amount
+            .saturating_sub(loan_info_header_data.total_amount_lended);
+
+        if remaining_loan_amount == 0 {
+            return Err(DassiError::BorrowerAlreadyFunded.into());
+        }
+
+        let amount_to_lend = amount_to_lend_input.min(remaining_loan_amount);
+
+        let min_lending_amount =
+            Self::get_min_lending_amount(loan_info_header_data.loan_mint_decimals, config)?;
+        // last lending of a loan can be smaller than min lending amount
+        if amount_to_lend < min_lending_amount && amount_to_lend != remaining_loan_amount {
+            return Err(DassiError::ExpectedAmountMismatch.into());
+        }
+
+        Ok(amount_to_lend)
+    }
+
+    // Records amount_lent, which is already in DassiCoin vault, as lent by lender in loan: a new lender slot in loan account,
+    // loan totals and lender position, which is created on first lending of lender in the loan
+    fn record_lending<'a>(
+        lender_main_pubkey: &Pubkey,
+        loan_info_storage_account: &AccountInfo<'a>,
+        lender_position_account: &AccountInfo<'a>,
+        payer_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        amount_lent: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // position is created on first lending of lender in this loan
+        let mut lender_position_data = if lender_position_account.data_len() == 0 {
+            let (expected_lender_position_account_pubkey, lender_position_bump_seed) =
+                utils::get_lender_position_address(
+                    lender_main_pubkey,
+                    loan_info_storage_account.key,
+                    program_id,
+                );
+
+            if expected_lender_position_account_pubkey != *lender_position_account.key {
+                return Err(DassiError::AccountMismatched.into());
+            }
+
+            msg!("Calling the system program to create lender position account...");
+            Self::create_program_account(
+                payer_account,
+                lender_position_account,
+                system_program,
+                LenderPositionAccount::LEN,
+                &[
+                    &b"DassiFinancePosition"[..],
+                    lender_main_pubkey.as_ref(),
+                    loan_info_storage_account.key.as_ref(),
+                    &[lender_position_bump_seed],
+                ],
+                program_id,
+            )?;
+
+            let mut lender_position_data =
+                LenderPositionAccount::unpack_unchecked(&lender_position_account.data.borrow())?;
+            lender_position_data.is_initialized = true;
+            lender_position_data.acc_type = AccTypes::LenderPositionAcc as u8;
+            lender_position_data.lender_main_acc_pubkey = *lender_main_pubkey;
+            lender_position_data.loan_info_acc_pubkey = *loan_info_storage_account.key;
+            lender_position_data.position_status = LenderPositionStatus::Active as u8;
+            lender_position_data
+        } else {
+            Self::load_lender_position_account(
+                lender_position_account,
+                lender_main_pubkey,
+                loan_info_storage_account.key,
+                program_id,
+            )?
+        };
+
+        lender_position_data.principal_lent = lender_position_data
+            .principal_lent
+            .checked_add(amount_lent)
+            .unwrap();
+        // lender may lend again after cancelling all he lent
+        lender_position_data.position_status = LenderPositionStatus::Active as u8;
+
+        LenderPositionAccount::pack(
+            lender_position_data,
+            &mut lender_position_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        // update lender payment in LoanInfoAccData
+        let mut loan_info_data_byte_array = loan_info_storage_account.try_borrow_mut_data()?;
+        let mut loan_info_header_data: LoanInfoAccDataHeader = state::unpack_to_loan_info_header(
+            &loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+        validation::check_loan_info_account(
+            loan_info_storage_account,
+            &loan_info_header_data,
+            program_id,
+        )?;
+
+        if loan_info_header_data.acc_type != AccTypes::LoanInfoAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp as u64;
+
+        if loan_info_header_data.fundraising_period_ending_timestamp < now
+            && loan_info_header_data.total_amount_lended < loan_info_header_data.total_loan_amount
+        {
+            // fundraising period expired, and loan is not fulfilled so, send funds back to lenders
+            return Err(DassiError::FundraisingPeriodExpired.into());
+        }
+
+        if Self::get_loan_status(&loan_info_header_data)? != LoanStatus::Fundraising {
+            return Err(DassiError::BorrowerAlreadyFunded.into());
+        }
+
+        // loan account has space for lent amounts of MAX_NUMBER_OF_LENDERS_PER_LOAN lenders only
+        if loan_info_header_data.next_index_to_store_lender_data >= state::MAX_NUMBER_OF_LENDERS_PER_LOAN {
+            return Err(DassiError::LoanLendersLimitReached.into());
+        }
+
+        let loan_info_lender_data_si: usize = state::LOAN_INFO_HEADER_DATA_BYTES
+            + (loan_info_header_data.next_index_to_store_lender_data as usize)
+                * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+        let loan_info_lender_data_ei: usize =
+            loan_info_lender_data_si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+        let mut loan_info_lender_data: LoanInfoAccLendersData =
+            state::unpack_to_loan_info_acc_lender_data(
+                &loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+            )
+            .unwrap();
+
+        loan_info_lender_data.lender_main_acc_pubkey = *lender_main_pubkey;
+        loan_info_lender_data.lent_amount = amount_lent;
+
+        state::pack_to_loan_info_acc_lender_data(
+            loan_info_lender_data,
+            &mut loan_info_data_byte_array[loan_info_lender_data_si..loan_info_lender_data_ei],
+        )
+        .unwrap();
+
+        loan_info_header_data.next_index_to_store_lender_data =
+            loan_info_header_data.next_index_to_store_lender_data + 1;
+        loan_info_header_data.total_amount_lended = loan_info_header_data
+            .total_amount_lended
+            .checked_add(amount_lent)
+            .unwrap();
+        if loan_info_header_data.total_amount_lended >= loan_info_header_data.total_loan_amount {
+            Self::transition_loan_status(&mut loan_info_header_data, LoanStatus::Funded)?;
+        }
+        state::pack_to_loan_info_header(
+            loan_info_header_data,
+            &mut loan_info_data_byte_array
+                [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+        )
+        .unwrap();
+
+        Ok(())
+    }
+
+    // smallest amount lenders can lend in, in base units of loan mint
+    fn get_lending_amount_unit(loan_mint_decimals: u8) -> Result<u64, ProgramError> {
+        utils::convert_amount_decimals(state::LENDING_AMOUNT_UNIT_TOKENS, 0, loan_mint_decimals)
+            .ok_or_else(|| DassiError::AmountOverflow.into())
+    }
+
+    // min lending amount of config, in base units of loan mint
+    fn get_min_lending_amount(
+        loan_mint_decimals: u8,
+        config: &ProtocolConfig,
+    ) -> Result<u64, ProgramError> {
+        utils::convert_amount_decimals(config.params.min_lending_amount, 0, loan_mint_decimals)
+            .ok_or_else(|| DassiError::AmountOverflow.into())
+    }
+
+    // protocol config is always the last account of an instruction
+    fn load_protocol_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> Result<ProtocolConfig, ProgramError> {
+        let config_account = accounts.last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        if config_account.owner != program_id
+            || *config_account.key != utils::get_protocol_config_address(program_id).0
+        {
+            return Err(DassiError::InvalidConfigAccount.into());
+        }
+
+        let config = ProtocolConfig::unpack(&config_account.data.borrow())?;
+        if config.acc_type != AccTypes::ConfigAcc as u8 {
+            return Err(DassiError::ExpectedAccountTypeMismatched.into());
+        }
+
+        Ok(config)
+    }
+
+    fn process_pause_operations(
+        accounts: &[AccountInfo],
+        operations_input: u8,
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pauser_account = next_account_info(account_info_iter)?;
+
+        // admin can always pause, even after handing pauser role to someone else
+        let role_pubkey = if *pauser_account.key == config.admin {
+            config.admin
+        } else {
+            config.pauser
+        };
+        Self::check_role(pauser_account, &role_pubkey, DassiError::PauserDoesNotMatched)?;
+
+        if operations_input == 0 || operations_input & !state::VALID_PAUSE_FLAGS != 0 {
+            return Err(DassiError::InvalidPauseFlags.into());
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        config.paused_operations |= operations_input;
+        msg!("paused operations: {:#010b}", config.paused_operations);
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    fn process_unpause_operations(
+        accounts: &[AccountInfo],
+        operations_input: u8,
+        mut config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_account = next_account_info(account_info_iter)?;
+
+        Self::check_role(admin_account, &config.admin, DassiError::AdminDoesNotMatched)?;
+
+        if operations_input == 0 || operations_input & !state::VALID_PAUSE_FLAGS != 0 {
+            return Err(DassiError::InvalidPauseFlags.into());
+        }
+
+        let config_account = next_account_info(account_info_iter)?;
+
+        config.paused_operations &= !operations_input;
+        msg!("paused operations: {:#010b}", config.paused_operations);
+        Self::store_protocol_config(config_account, config, program_id)
+    }
+
+    // pause flag which stops the instruction, None if it can't be paused
+    fn get_pausable_operation(instruction: &DassiInstruction) -> Option<u8> {
+        match instruction {
+            DassiInstruction::LendToBorrower { .. }
+            | DassiInstruction::RelendFromFreeBalance { .. }
+            | DassiInstruction::AllocateFreeBalanceToLoans { .. } => Some(state::PAUSE_LENDING),
+            DassiInstruction::WithdrawLenderFreeWalletFunds { .. }
+            | DassiInstruction::UnstakeGuarantorCollateral { .. }
+            | DassiInstruction::CancelLendingCommitment { .. } => Some(state::PAUSE_WITHDRAWALS),
+            DassiInstruction::WithdrawCollectedLoanFunds {} => Some(state::PAUSE_DISBURSEMENTS),
+            DassiInstruction::AirdropUsersWithDassiTestCoins {} => Some(state::PAUSE_AIRDROPS),
+            DassiInstruction::PayEMIforLoan { .. }
+            | DassiInstruction::PayDefaultedLoanAsGuarantor { .. } => Some(state::PAUSE_REPAYMENTS),
+            _ => None,
+        }
+    }
+
+    fn store_protocol_config(
+        config_account: &AccountInfo,
+        config: ProtocolConfig,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if *config_account.key != utils::get_protocol_config_address(program_id).0 {
+            return Err(DassiError::InvalidConfigAccount.into());
+        }
+        ProtocolConfig::pack(config, &mut config_account.data.borrow_mut())?;
+        Ok(())
+    }
+
+    // account holding a role has to sign the instruction
+    fn check_role(
+        role_account: &AccountInfo,
+        role_pubkey: &Pubkey,
+        error: DassiError,
+    ) -> ProgramResult {
+        if !role_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if role_account.key != role_pubkey {
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    fn validate_protocol_config_params(params: &ProtocolConfigParams) -> ProgramResult {
+        let basis_points = amortization::BASIS_POINTS as u16;
+        if params.min_lending_amount == 0
+            || params.initial_credit_score > credit_score::MAX_CREDIT_SCORE
+            || params.late_fee_bps > basis_points
+            || params.guarantor_stake_coverage_bps > basis_points
+            || params.late_payment_grace_period_days > amortization::MAX_CUSTOM_EMI_INTERVAL_DAYS
+        {
+            return Err(DassiError::InvalidConfigParameters.into());
+        }
+        Ok(())
+    }
+
+    fn get_loan_status(
+        loan_info_header_data: &LoanInfoAccDataHeader,
+    ) -> Result<LoanStatus, ProgramError> {
+        LoanStatus::from_u8(loan_info_header_data.loan_status)
+            .ok_or_else(|| DassiError::UnexpectedLoanStatus.into())
+    }
+
+    // every change of loan status goes through here, so a loan can never skip a step of its lifecycle
+    fn transition_loan_status(
+        loan_info_header_data: &mut LoanInfoAccDataHeader,
+        next_status: LoanStatus,
+    ) -> ProgramResult {
+        let current_status = Self::get_loan_status(loan_info_header_data)?;
+        if !current_status.can_transition_to(next_status) {
+            msg!(
+                "loan status can't change from {:?} to {:?}",
+                current_status,
+                next_status
+            );
+            return Err(DassiError::InvalidLoanStatusTransition.into());
+        }
+        msg!("loan status changed from {:?} to {:?}", current_status, next_status);
+        loan_info_header_data.loan_status = next_status as u8;
+        Ok(())
+    }
+
+    // Creates a rent exempt pda owned by owner. Anyone can send lamports to a pda address before it is created, which would
+    // make create_account fail, so in that case only the missing rent is transferred and the account is allocated and assigned
+    fn create_program_account<'a>(
+        payer_account: &AccountInfo<'a>,
+        new_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        data_len: usize,
+        signer_seeds: &[&[u8]],
+        owner: &Pubkey,
+    ) -> ProgramResult {
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *system_program.key != solana_program::system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let rent = Rent::get()?;
+        let required_lamports = rent.minimum_balance(data_len);
+
+        if new_account.lamports() == 0 {
+            return invoke_signed(
+                &system_instruction::create_account(
+                    payer_account.key,
+                    new_account.key,
+                    required_lamports,
+                    data_len as u64,
+                    owner,
+                ),
+                &[
+                    payer_account.clone(),
+                    new_account.clone(),
+                    system_program.clone(),
+                ],
+                &[signer_seeds],
+            );
+        }
+
+        let lamports_to_transfer = required_lamports.saturating_sub(new_account.lamports());
+        if lamports_to_transfer > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    payer_account.key,
+                    new_account.key,
+                    lamports_to_transfer,
+                ),
+                &[
+                    payer_account.clone(),
+                    new_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(new_account.key, data_len as u64),
+            &[new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(new_account.key, owner),
+            &[new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )
+    }
+
+    // unlocks guarantor stake backing this loan, guarantor can unstake it afterwards
+    fn release_guarantor_stake(
+        guarantor_stake_account: &AccountInfo,
+        loan_info_header_data: &mut LoanInfoAccDataHeader,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let mut guarantor_stake_data = Self::load_guarantor_stake_account(
+            guarantor_stake_account,
+            &loan_info_header_data.guarantor_main_acc_pubkey,
+            &loan_info_header_data.loan_mint,
+            program_id,
+        )?;
+
+        guarantor_stake_data.locked_stake_amount = guarantor_stake_data
+            .locked_stake_amount
+            .checked_sub(loan_info_header_data.guarantor_stake_locked_amount)
+            .unwrap();
+        msg!(
+            "released guarantor stake {}",
+            loan_info_header_data.guarantor_stake_locked_amount
+        );
+        loan_info_header_data.guarantor_stake_locked_amount = 0u64;
+
+        GuarantorStakeAccount::pack(
+            guarantor_stake_data,
+            &mut guarantor_stake_account.data.try_borrow_mut().unwrap(),
+        )?;
+
+        Ok(())
+    }
+
+    fn get_loan_terms(
+        loan_info_header_data: &LoanInfoAccDataHeader,
+    ) -> Result<LoanTerms, ProgramError> {
+        Ok(LoanTerms {
+            amortization_type: AmortizationType::from_u8(loan_info_header_data.amortization_type)
+                .ok_or(DassiError::InvalidLoanParameters)?,
+            total_loan_amount: loan_info_header_data.total_loan_amount,
+            annual_interest_rate_bps: loan_info_header_data.annual_interest_rate_bps,
+            number_of_emis: loan_info_header_data.number_of_emis_needed_to_repay_the_loan,
+            emi_interval_days: loan_info_header_data.emi_interval_days as u64,
+        })
+    }
 }
@@ -6,18 +6,27 @@ use solana_program::{
 
 use crate::error::DassiError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use borsh::{BorshDeserialize, BorshSerialize};
 pub enum AccTypes {
     BorrowerAcc = 2,
     LendersAcc = 3,
     GuarantorAcc = 4,
     LoanInfoAcc = 5,
+    ConfigAcc = 6,
+    LenderAcc = 7,
+    LenderPositionAcc = 8,
+    GuarantorStakeAcc = 9,
 }
 // AccTypes::LenderAcc as u8
 
-// total bytes needed to store LoanInfoAccData = 116 + 9000 + 800 = 9916
-pub const LOAN_INFO_ACC_DATA_SIZE: usize = 9916;
-// total bytes needed to store lender data = 65*50_000 + 2
-pub const LENDERS_STORAGE_ACC_DATA_SIZE: usize = 3_250_002;
+// total bytes needed to store LoanInfoAccData = 241 + 8000 + 900 = 9141
+// Loan accounts of the deployed program (9916 bytes, 116 byte header, 45 byte lender slots, not at "DassiFinanceLoan" pda)
+// can't be read by this program and there is no legacy reader for them. Every deployed loan must be repaid, or refunded to
+// its lenders, with the deployed program before upgrading, otherwise funds lent to it can't be returned. Borrower and
+// guarantor accounts of the deployed program aren't read either, borrowers and guarantors create new ones at their pdas
+pub const LOAN_INFO_ACC_DATA_SIZE: usize = 9141;
+// size of legacy lenders storage account = 65*50_000 + 2, its lenders are migrated to LenderAccount pdas
+pub const LEGACY_LENDERS_STORAGE_ACC_DATA_SIZE: usize = 3_250_002;
 pub struct BorrowerAccount {
     pub is_initialized: bool,
     pub acc_type: u8,
@@ -25,6 +34,8 @@ pub struct BorrowerAccount {
     pub borrower_main_acc_pubkey: Pubkey,
     pub credit_score: u64,
     pub active_loan_address: Pubkey,
+    // nonce of the next loan account of this borrower, incremented for every new loan
+    pub next_loan_nonce: u64,
 }
 
 impl Sealed for BorrowerAccount {}
@@ -36,7 +47,7 @@ impl IsInitialized for BorrowerAccount {
 }
 
 impl Pack for BorrowerAccount {
-    const LEN: usize = 75;
+    const LEN: usize = 83;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, BorrowerAccount::LEN];
         let (
@@ -46,7 +57,8 @@ impl Pack for BorrowerAccount {
             borrower_main_acc_pubkey_src,
             credit_score_src,
             active_loan_address_src,
-        ) = array_refs![src, 1, 1, 1, 32, 8, 32];
+            next_loan_nonce_src,
+        ) = array_refs![src, 1, 1, 1, 32, 8, 32, 8];
 
         let is_initialized = match is_initialized_src {
             [0] => false,
@@ -61,6 +73,7 @@ impl Pack for BorrowerAccount {
             borrower_main_acc_pubkey: Pubkey::new_from_array(*borrower_main_acc_pubkey_src),
             credit_score: u64::from_le_bytes(*credit_score_src),
             active_loan_address: Pubkey::new_from_array(*active_loan_address_src),
+            next_loan_nonce: u64::from_le_bytes(*next_loan_nonce_src),
         })
     }
 
@@ -73,7 +86,8 @@ impl Pack for BorrowerAccount {
             borrower_main_acc_pubkey_dst,
             credit_score_dst,
             active_loan_address_dst,
-        ) = mut_array_refs![dst, 1, 1, 1, 32, 8, 32];
+            next_loan_nonce_dst,
+        ) = mut_array_refs![dst, 1, 1, 1, 32, 8, 32, 8];
         let BorrowerAccount {
             is_initialized,
             acc_type,
@@ -81,6 +95,7 @@ impl Pack for BorrowerAccount {
             borrower_main_acc_pubkey,
             credit_score,
             active_loan_address,
+            next_loan_nonce,
         } = self;
         is_initialized_dst[0] = *is_initialized as u8;
         type_dst[0] = *acc_type as u8;
@@ -88,6 +103,7 @@ impl Pack for BorrowerAccount {
         borrower_main_acc_pubkey_dst.copy_from_slice(borrower_main_acc_pubkey.as_ref());
         *credit_score_dst = credit_score.to_le_bytes();
         active_loan_address_dst.copy_from_slice(active_loan_address.as_ref());
+        *next_loan_nonce_dst = next_loan_nonce.to_le_bytes();
     }
 }
 
@@ -144,24 +160,593 @@ impl Pack for GuarantorAccount {
     }
 }
 
-// LoanInfoAccDataHeader has 116 bytes of data
+// Stake of a guarantor in one mint (pda: "DassiFinanceGuarantorStake", guarantor main account, mint), its tokens are held
+// in stake vault of this account. A loan locks stake in its own loan mint, so slashed stake can be credited to lenders as
+// it is
+pub struct GuarantorStakeAccount {
+    pub is_initialized: bool,
+    pub acc_type: u8,
+    pub guarantor_main_acc_pubkey: Pubkey,
+    pub mint: Pubkey,
+    // tokens of mint held in stake vault
+    pub staked_amount: u64,
+    // part of staked_amount backing active loans, it can't be unstaked
+    pub locked_stake_amount: u64,
+}
+
+impl Sealed for GuarantorStakeAccount {}
+
+impl IsInitialized for GuarantorStakeAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for GuarantorStakeAccount {
+    const LEN: usize = 82;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, GuarantorStakeAccount::LEN];
+        let (
+            is_initialized_src,
+            type_src,
+            guarantor_main_acc_pubkey_src,
+            mint_src,
+            staked_amount_src,
+            locked_stake_amount_src,
+        ) = array_refs![src, 1, 1, 32, 32, 8, 8];
+
+        let is_initialized = match is_initialized_src {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(GuarantorStakeAccount {
+            is_initialized,
+            acc_type: type_src[0],
+            guarantor_main_acc_pubkey: Pubkey::new_from_array(*guarantor_main_acc_pubkey_src),
+            mint: Pubkey::new_from_array(*mint_src),
+            staked_amount: u64::from_le_bytes(*staked_amount_src),
+            locked_stake_amount: u64::from_le_bytes(*locked_stake_amount_src),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, GuarantorStakeAccount::LEN];
+        let (
+            is_initialized_dst,
+            type_dst,
+            guarantor_main_acc_pubkey_dst,
+            mint_dst,
+            staked_amount_dst,
+            locked_stake_amount_dst,
+        ) = mut_array_refs![dst, 1, 1, 32, 32, 8, 8];
+        let GuarantorStakeAccount {
+            is_initialized,
+            acc_type,
+            guarantor_main_acc_pubkey,
+            mint,
+            staked_amount,
+            locked_stake_amount,
+        } = self;
+        is_initialized_dst[0] = *is_initialized as u8;
+        type_dst[0] = *acc_type;
+        guarantor_main_acc_pubkey_dst.copy_from_slice(guarantor_main_acc_pubkey.as_ref());
+        mint_dst.copy_from_slice(mint.as_ref());
+        *staked_amount_dst = staked_amount.to_le_bytes();
+        *locked_stake_amount_dst = locked_stake_amount.to_le_bytes();
+    }
+}
+
+// Every lender has its own account per mint (pda: "DassiFinanceLender", lender main account, mint)
+// total_lending_amount can be act as lending_score
+pub struct LenderAccount {
+    pub is_initialized: bool,
+    pub acc_type: u8,
+    pub lender_main_acc_pubkey: Pubkey,
+    // mint of every amount of this account
+    pub mint: Pubkey,
+    pub total_lending_amount: u128,
+    pub total_unique_lending_amount: u64,
+    // free balance, lender can withdraw it or lend it again
+    pub amount_to_withdraw_or_lend: u64,
+    // part of repayments credited to amount_to_withdraw_or_lend which was interest
+    pub total_interest_earned: u64,
+    // Lending strategy, AllocateFreeBalanceToLoans lends free balance to loans matching it. It is enabled while
+    // strategy_max_amount_per_loan is not 0
+    pub strategy_max_amount_per_loan: u64,
+    pub strategy_min_credit_score: u64,
+    pub strategy_min_approval_score: u64,
+}
+
+impl Sealed for LenderAccount {}
+
+impl IsInitialized for LenderAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for LenderAccount {
+    const LEN: usize = 130;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, LenderAccount::LEN];
+        let (
+            is_initialized_src,
+            type_src,
+            lender_main_acc_pubkey_src,
+            mint_src,
+            total_lending_amount_src,
+            total_unique_lending_amount_src,
+            amount_to_withdraw_or_lend_src,
+            total_interest_earned_src,
+            strategy_max_amount_per_loan_src,
+            strategy_min_credit_score_src,
+            strategy_min_approval_score_src,
+        ) = array_refs![src, 1, 1, 32, 32, 16, 8, 8, 8, 8, 8, 8];
+
+        let is_initialized = match is_initialized_src {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(LenderAccount {
+            is_initialized,
+            acc_type: type_src[0],
+            lender_main_acc_pubkey: Pubkey::new_from_array(*lender_main_acc_pubkey_src),
+            mint: Pubkey::new_from_array(*mint_src),
+            total_lending_amount: u128::from_le_bytes(*total_lending_amount_src),
+            total_unique_lending_amount: u64::from_le_bytes(*total_unique_lending_amount_src),
+            amount_to_withdraw_or_lend: u64::from_le_bytes(*amount_to_withdraw_or_lend_src),
+            total_interest_earned: u64::from_le_bytes(*total_interest_earned_src),
+            strategy_max_amount_per_loan: u64::from_le_bytes(*strategy_max_amount_per_loan_src),
+            strategy_min_credit_score: u64::from_le_bytes(*strategy_min_credit_score_src),
+            strategy_min_approval_score: u64::from_le_bytes(*strategy_min_approval_score_src),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, LenderAccount::LEN];
+        let (
+            is_initialized_dst,
+            type_dst,
+            lender_main_acc_pubkey_dst,
+            mint_dst,
+            total_lending_amount_dst,
+            total_unique_lending_amount_dst,
+            amount_to_withdraw_or_lend_dst,
+            total_interest_earned_dst,
+            strategy_max_amount_per_loan_dst,
+            strategy_min_credit_score_dst,
+            strategy_min_approval_score_dst,
+        ) = mut_array_refs![dst, 1, 1, 32, 32, 16, 8, 8, 8, 8, 8, 8];
+        let LenderAccount {
+            is_initialized,
+            acc_type,
+            lender_main_acc_pubkey,
+            mint,
+            total_lending_amount,
+            total_unique_lending_amount,
+            amount_to_withdraw_or_lend,
+            total_interest_earned,
+            strategy_max_amount_per_loan,
+            strategy_min_credit_score,
+            strategy_min_approval_score,
+        } = self;
+        is_initialized_dst[0] = *is_initialized as u8;
+        type_dst[0] = *acc_type;
+        lender_main_acc_pubkey_dst.copy_from_slice(lender_main_acc_pubkey.as_ref());
+        mint_dst.copy_from_slice(mint.as_ref());
+        *total_lending_amount_dst = total_lending_amount.to_le_bytes();
+        *total_unique_lending_amount_dst = total_unique_lending_amount.to_le_bytes();
+        *amount_to_withdraw_or_lend_dst = amount_to_withdraw_or_lend.to_le_bytes();
+        *total_interest_earned_dst = total_interest_earned.to_le_bytes();
+        *strategy_max_amount_per_loan_dst = strategy_max_amount_per_loan.to_le_bytes();
+        *strategy_min_credit_score_dst = strategy_min_credit_score.to_le_bytes();
+        *strategy_min_approval_score_dst = strategy_min_approval_score.to_le_bytes();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LenderPositionStatus {
+    Active = 1,
+    // all principal lent in the loan is returned to lender
+    Repaid = 2,
+    // fundraising failed and lent amount is returned to lender
+    Refunded = 3,
+    // lender cancelled everything lent in the loan while it was raising funds
+    Cancelled = 4,
+}
+
+// Fund position of a lender in one loan (pda: "DassiFinancePosition", lender main account, loan info account).
+// Amounts cover every lending of the lender in the loan
+pub struct LenderPositionAccount {
+    pub is_initialized: bool,
+    pub acc_type: u8,
+    pub lender_main_acc_pubkey: Pubkey,
+    pub loan_info_acc_pubkey: Pubkey,
+    pub principal_lent: u64,
+    pub principal_returned: u64,
+    pub interest_earned: u64,
+    pub position_status: u8,
+}
+
+impl Sealed for LenderPositionAccount {}
+
+impl IsInitialized for LenderPositionAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for LenderPositionAccount {
+    const LEN: usize = 91;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, LenderPositionAccount::LEN];
+        let (
+            is_initialized_src,
+            type_src,
+            lender_main_acc_pubkey_src,
+            loan_info_acc_pubkey_src,
+            principal_lent_src,
+            principal_returned_src,
+            interest_earned_src,
+            position_status_src,
+        ) = array_refs![src, 1, 1, 32, 32, 8, 8, 8, 1];
+
+        let is_initialized = match is_initialized_src {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(LenderPositionAccount {
+            is_initialized,
+            acc_type: type_src[0],
+            lender_main_acc_pubkey: Pubkey::new_from_array(*lender_main_acc_pubkey_src),
+            loan_info_acc_pubkey: Pubkey::new_from_array(*loan_info_acc_pubkey_src),
+            principal_lent: u64::from_le_bytes(*principal_lent_src),
+            principal_returned: u64::from_le_bytes(*principal_returned_src),
+            interest_earned: u64::from_le_bytes(*interest_earned_src),
+            position_status: position_status_src[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, LenderPositionAccount::LEN];
+        let (
+            is_initialized_dst,
+            type_dst,
+            lender_main_acc_pubkey_dst,
+            loan_info_acc_pubkey_dst,
+            principal_lent_dst,
+            principal_returned_dst,
+            interest_earned_dst,
+            position_status_dst,
+        ) = mut_array_refs![dst, 1, 1, 32, 32, 8, 8, 8, 1];
+        let LenderPositionAccount {
+            is_initialized,
+            acc_type,
+            lender_main_acc_pubkey,
+            loan_info_acc_pubkey,
+            principal_lent,
+            principal_returned,
+            interest_earned,
+            position_status,
+        } = self;
+        is_initialized_dst[0] = *is_initialized as u8;
+        type_dst[0] = *acc_type;
+        lender_main_acc_pubkey_dst.copy_from_slice(lender_main_acc_pubkey.as_ref());
+        loan_info_acc_pubkey_dst.copy_from_slice(loan_info_acc_pubkey.as_ref());
+        *principal_lent_dst = principal_lent.to_le_bytes();
+        *principal_returned_dst = principal_returned.to_le_bytes();
+        *interest_earned_dst = interest_earned.to_le_bytes();
+        position_status_dst[0] = *position_status;
+    }
+}
+
+// Protocol parameters which admin can change with UpdateConfig, also passed as instruction data so they are borsh encoded
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ProtocolConfigParams {
+    // DassiCoin mint, airdrops are paid in it
+    pub accepted_mint: Pubkey,
+    // in whole tokens of loan mint, as loans can be in mints with different decimals
+    pub min_lending_amount: u64,
+    pub airdrop_amount: u64,
+    pub max_airdrop_amount_per_user: u64,
+    // a user can pay upto these many days after due date without being late
+    pub late_payment_grace_period_days: u16,
+    pub initial_credit_score: u64,
+    pub late_fee_bps: u16,
+    // guarantor has to lock stake worth this part of total loan amount to approve a loan
+    pub guarantor_stake_coverage_bps: u16,
+    // whitelist of mints loans can be created in, unused entries are default pubkey
+    pub accepted_loan_mints: [Pubkey; MAX_ACCEPTED_LOAN_MINTS],
+}
+
+pub const MAX_ACCEPTED_LOAN_MINTS: usize = 4;
+
+// bits of ProtocolConfig paused_operations
+pub const PAUSE_LENDING: u8 = 1 << 0;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1;
+pub const PAUSE_DISBURSEMENTS: u8 = 1 << 2;
+pub const PAUSE_AIRDROPS: u8 = 1 << 3;
+pub const PAUSE_REPAYMENTS: u8 = 1 << 4;
+// global pause, stops every pausable operation except repayments, which stay open unless PAUSE_REPAYMENTS is set too so
+// borrowers are not pushed into late status by a pause
+pub const PAUSE_ALL: u8 = 1 << 7;
+pub const VALID_PAUSE_FLAGS: u8 =
+    PAUSE_LENDING | PAUSE_WITHDRAWALS | PAUSE_DISBURSEMENTS | PAUSE_AIRDROPS | PAUSE_REPAYMENTS | PAUSE_ALL;
+
+// Roles: admin manages roles and protocol vaults, pauser can pause the protocol and risk manager changes protocol parameters.
+// Admin is handed over in two steps, current admin proposes pending_admin which then has to accept, default pubkey means
+// no handover is pending
+pub struct ProtocolConfig {
+    pub is_initialized: bool,
+    pub acc_type: u8,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub pauser: Pubkey,
+    pub risk_manager: Pubkey,
+    pub paused_operations: u8,
+    // set once MigrateLegacyVault moved balance of deployed DassiCoin vault to vault of DassiCoin mint
+    pub legacy_vault_migrated: bool,
+    pub params: ProtocolConfigParams,
+}
+
+impl ProtocolConfig {
+    pub fn is_paused(&self, operation: u8) -> bool {
+        self.paused_operations & operation != 0
+            || (self.paused_operations & PAUSE_ALL != 0 && operation != PAUSE_REPAYMENTS)
+    }
+
+    pub fn is_accepted_loan_mint(&self, mint: &Pubkey) -> bool {
+        *mint != Pubkey::default() && self.params.accepted_loan_mints.contains(mint)
+    }
+}
+
+impl Sealed for ProtocolConfig {}
+
+impl IsInitialized for ProtocolConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for ProtocolConfig {
+    const LEN: usize = 330;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ProtocolConfig::LEN];
+        let (
+            is_initialized_src,
+            type_src,
+            admin_src,
+            pending_admin_src,
+            pauser_src,
+            risk_manager_src,
+            paused_operations_src,
+            legacy_vault_migrated_src,
+            accepted_mint_src,
+            min_lending_amount_src,
+            airdrop_amount_src,
+            max_airdrop_amount_per_user_src,
+            late_payment_grace_period_days_src,
+            initial_credit_score_src,
+            late_fee_bps_src,
+            guarantor_stake_coverage_bps_src,
+            accepted_loan_mints_src,
+        ) = array_refs![src, 1, 1, 32, 32, 32, 32, 1, 1, 32, 8, 8, 8, 2, 8, 2, 2, 128];
+
+        let is_initialized = match is_initialized_src {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let legacy_vault_migrated = match legacy_vault_migrated_src {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let mut accepted_loan_mints = [Pubkey::default(); MAX_ACCEPTED_LOAN_MINTS];
+        for (index, accepted_loan_mint) in accepted_loan_mints.iter_mut().enumerate() {
+            *accepted_loan_mint =
+                Pubkey::new(&accepted_loan_mints_src[index * 32..(index + 1) * 32]);
+        }
+
+        Ok(ProtocolConfig {
+            is_initialized,
+            acc_type: type_src[0],
+            admin: Pubkey::new_from_array(*admin_src),
+            pending_admin: Pubkey::new_from_array(*pending_admin_src),
+            pauser: Pubkey::new_from_array(*pauser_src),
+            risk_manager: Pubkey::new_from_array(*risk_manager_src),
+            paused_operations: paused_operations_src[0],
+            legacy_vault_migrated,
+            params: ProtocolConfigParams {
+                accepted_mint: Pubkey::new_from_array(*accepted_mint_src),
+                min_lending_amount: u64::from_le_bytes(*min_lending_amount_src),
+                airdrop_amount: u64::from_le_bytes(*airdrop_amount_src),
+                max_airdrop_amount_per_user: u64::from_le_bytes(*max_airdrop_amount_per_user_src),
+                late_payment_grace_period_days: u16::from_le_bytes(
+                    *late_payment_grace_period_days_src,
+                ),
+                initial_credit_score: u64::from_le_bytes(*initial_credit_score_src),
+                late_fee_bps: u16::from_le_bytes(*late_fee_bps_src),
+                guarantor_stake_coverage_bps: u16::from_le_bytes(*guarantor_stake_coverage_bps_src),
+                accepted_loan_mints,
+            },
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ProtocolConfig::LEN];
+        let (
+            is_initialized_dst,
+            type_dst,
+            admin_dst,
+            pending_admin_dst,
+            pauser_dst,
+            risk_manager_dst,
+            paused_operations_dst,
+            legacy_vault_migrated_dst,
+            accepted_mint_dst,
+            min_lending_amount_dst,
+            airdrop_amount_dst,
+            max_airdrop_amount_per_user_dst,
+            late_payment_grace_period_days_dst,
+            initial_credit_score_dst,
+            late_fee_bps_dst,
+            guarantor_stake_coverage_bps_dst,
+            accepted_loan_mints_dst,
+        ) = mut_array_refs![dst, 1, 1, 32, 32, 32, 32, 1, 1, 32, 8, 8, 8, 2, 8, 2, 2, 128];
+        let ProtocolConfig {
+            is_initialized,
+            acc_type,
+            admin,
+            pending_admin,
+            pauser,
+            risk_manager,
+            paused_operations,
+            legacy_vault_migrated,
+            params,
+        } = self;
+        is_initialized_dst[0] = *is_initialized as u8;
+        type_dst[0] = *acc_type;
+        admin_dst.copy_from_slice(admin.as_ref());
+        pending_admin_dst.copy_from_slice(pending_admin.as_ref());
+        pauser_dst.copy_from_slice(pauser.as_ref());
+        risk_manager_dst.copy_from_slice(risk_manager.as_ref());
+        paused_operations_dst[0] = *paused_operations;
+        legacy_vault_migrated_dst[0] = *legacy_vault_migrated as u8;
+        accepted_mint_dst.copy_from_slice(params.accepted_mint.as_ref());
+        *min_lending_amount_dst = params.min_lending_amount.to_le_bytes();
+        *airdrop_amount_dst = params.airdrop_amount.to_le_bytes();
+        *max_airdrop_amount_per_user_dst = params.max_airdrop_amount_per_user.to_le_bytes();
+        *late_payment_grace_period_days_dst = params.late_payment_grace_period_days.to_le_bytes();
+        *initial_credit_score_dst = params.initial_credit_score.to_le_bytes();
+        *late_fee_bps_dst = params.late_fee_bps.to_le_bytes();
+        *guarantor_stake_coverage_bps_dst = params.guarantor_stake_coverage_bps.to_le_bytes();
+        for (index, accepted_loan_mint) in params.accepted_loan_mints.iter().enumerate() {
+            accepted_loan_mints_dst[index * 32..(index + 1) * 32]
+                .copy_from_slice(accepted_loan_mint.as_ref());
+        }
+    }
+}
+
+// Lifecycle of a loan:
+// Fundraising -> Funded -> Disbursed -> Repaying -> Repaid -> Closed
+// Fundraising -> Refunding -> Refunded -> Closed, when fundraising period expires before loan is funded
+// Disbursed / Repaying -> Defaulted -> Closed, when borrower misses emis and guarantor settles the loan
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoanStatus {
+    Fundraising = 0,
+    Funded = 1,
+    // borrower has withdrawn collected loan funds
+    Disbursed = 2,
+    Repaying = 3,
+    Repaid = 4,
+    Defaulted = 5,
+    Refunding = 6,
+    Refunded = 7,
+    Closed = 8,
+}
+
+impl LoanStatus {
+    pub fn from_u8(input: u8) -> Option<Self> {
+        match input {
+            0 => Some(LoanStatus::Fundraising),
+            1 => Some(LoanStatus::Funded),
+            2 => Some(LoanStatus::Disbursed),
+            3 => Some(LoanStatus::Repaying),
+            4 => Some(LoanStatus::Repaid),
+            5 => Some(LoanStatus::Defaulted),
+            6 => Some(LoanStatus::Refunding),
+            7 => Some(LoanStatus::Refunded),
+            8 => Some(LoanStatus::Closed),
+            _ => None,
+        }
+    }
+
+    pub fn can_transition_to(&self, next_status: LoanStatus) -> bool {
+        matches!(
+            (self, next_status),
+            (LoanStatus::Fundraising, LoanStatus::Funded)
+                | (LoanStatus::Fundraising, LoanStatus::Refunding)
+                | (LoanStatus::Funded, LoanStatus::Disbursed)
+                | (LoanStatus::Disbursed, LoanStatus::Repaying)
+                | (LoanStatus::Disbursed, LoanStatus::Repaid)
+                | (LoanStatus::Disbursed, LoanStatus::Defaulted)
+                | (LoanStatus::Repaying, LoanStatus::Repaid)
+                | (LoanStatus::Repaying, LoanStatus::Defaulted)
+                | (LoanStatus::Refunding, LoanStatus::Refunded)
+                | (LoanStatus::Repaid, LoanStatus::Closed)
+                | (LoanStatus::Refunded, LoanStatus::Closed)
+                | (LoanStatus::Defaulted, LoanStatus::Closed)
+        )
+    }
+}
+
+// LoanInfoAccDataHeader has 241 bytes of data
 pub struct LoanInfoAccDataHeader {
     pub acc_type: u8,
     pub borrower_main_acc_pubkey: Pubkey,
     pub guarantor_main_acc_pubkey: Pubkey,
     pub loan_approval_timestamp: u64,
     pub fundraising_period_ending_timestamp: u64,
+    // due date of first emi, emi paid after due date + grace period is late
     pub first_repayment_last_date_timestamp: u64,
     pub total_loan_amount: u64,
     pub total_amount_lended: u64,
     pub repaid_amount_by_borrower: u64,
     pub next_index_to_store_lender_data: u8,
+    // index of next emi which is not fully paid yet, LoanInfoAccRepaymentData is stored per emi of the schedule
     pub next_index_to_store_repayment_info: u8,
     pub number_of_emis_needed_to_repay_the_loan: u8,
+    // index of next lender to refund when fundraising period expired without fulfilling the loan,
+    // refunds can be done in several transactions so we store where the last one stopped
+    pub next_index_to_return_funds: u8,
+    // index of lender who gets first dust token unit left after pro rata split of next emi
+    pub next_index_to_receive_dust: u8,
+    pub annual_interest_rate_bps: u16,
+    // amortization::AmortizationType as u8
+    pub amortization_type: u8,
+    // repaid_amount_by_borrower = principal_repaid_by_borrower + interest_paid_by_borrower + late_fees_paid_by_borrower
+    pub principal_repaid_by_borrower: u64,
+    pub interest_paid_by_borrower: u64,
+    // amortization::RepaymentIntervalType as u8
+    pub repayment_interval_type: u8,
+    // emi number i (starting from 0) is due at first_repayment_last_date_timestamp + i * emi_interval_days
+    pub emi_interval_days: u16,
+    pub number_of_emis_paid_on_time: u8,
+    pub number_of_emis_paid_late: u8,
+    pub late_fees_paid_by_borrower: u64,
+    // LoanStatus as u8
+    pub loan_status: u8,
+    // principal paid by guarantor after loan was defaulted
+    pub guarantor_repaid_amount: u64,
+    // part of guarantor stake in loan mint locked as collateral of this loan, 0 once it is released or slashed
+    pub guarantor_stake_locked_amount: u64,
+    // loan account is a pda of borrower main account and this nonce
+    pub loan_nonce: u64,
+    // Repayments are credited to lender accounts in rounds by CreditRepaymentsToLenders, as a loan can have more lenders than fit
+    // in one transaction. Amount paid towards loan waits in pending amounts until next round starts, then it is split pro rata
+    // between lenders and credited to next_index_to_credit lender onwards, which can take several transactions
+    pub lenders_pending_credit_amount: u64,
+    // part of pending credit amount which is interest or late fee
+    pub lenders_pending_earning_amount: u64,
+    pub lenders_round_credit_amount: u64,
+    pub lenders_round_earning_amount: u64,
+    pub next_index_to_credit: u8,
+    // mint loan is raised and repaid in, one of accepted loan mints of config when loan was created
+    pub loan_mint: Pubkey,
+    // decimals of loan_mint read from its mint account
+    pub loan_mint_decimals: u8,
 }
 
 pub fn unpack_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccDataHeader, ProgramError> {
-    const LOAN_INFO_HEADER_DATA_BYTES: usize = 116;
     if input.len() != LOAN_INFO_HEADER_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
     }
@@ -179,7 +764,29 @@ pub fn unpack_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccDataHeader,
         next_index_to_store_lender_data_src,
         next_index_to_store_repayment_info_src,
         number_of_emis_needed_to_repay_the_loan_src,
-    ) = array_refs![input, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1];
+        next_index_to_return_funds_src,
+        next_index_to_receive_dust_src,
+        annual_interest_rate_bps_src,
+        amortization_type_src,
+        principal_repaid_by_borrower_src,
+        interest_paid_by_borrower_src,
+        repayment_interval_type_src,
+        emi_interval_days_src,
+        number_of_emis_paid_on_time_src,
+        number_of_emis_paid_late_src,
+        late_fees_paid_by_borrower_src,
+        loan_status_src,
+        guarantor_repaid_amount_src,
+        guarantor_stake_locked_amount_src,
+        loan_nonce_src,
+        lenders_pending_credit_amount_src,
+        lenders_pending_earning_amount_src,
+        lenders_round_credit_amount_src,
+        lenders_round_earning_amount_src,
+        next_index_to_credit_src,
+        loan_mint_src,
+        loan_mint_decimals_src,
+    ) = array_refs![input, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1, 1, 1, 2, 1, 8, 8, 1, 2, 1, 1, 8, 1, 8, 8, 8, 8, 8, 8, 8, 1, 32, 1];
 
     if acc_type_src[0] != AccTypes::LoanInfoAcc as u8 {
         return Err(DassiError::ExpectedAccountTypeMismatched.into());
@@ -202,12 +809,33 @@ pub fn unpack_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccDataHeader,
         next_index_to_store_lender_data: next_index_to_store_lender_data_src[0],
         next_index_to_store_repayment_info: next_index_to_store_repayment_info_src[0],
         number_of_emis_needed_to_repay_the_loan: number_of_emis_needed_to_repay_the_loan_src[0],
+        next_index_to_return_funds: next_index_to_return_funds_src[0],
+        next_index_to_receive_dust: next_index_to_receive_dust_src[0],
+        annual_interest_rate_bps: u16::from_le_bytes(*annual_interest_rate_bps_src),
+        amortization_type: amortization_type_src[0],
+        principal_repaid_by_borrower: u64::from_le_bytes(*principal_repaid_by_borrower_src),
+        interest_paid_by_borrower: u64::from_le_bytes(*interest_paid_by_borrower_src),
+        repayment_interval_type: repayment_interval_type_src[0],
+        emi_interval_days: u16::from_le_bytes(*emi_interval_days_src),
+        number_of_emis_paid_on_time: number_of_emis_paid_on_time_src[0],
+        number_of_emis_paid_late: number_of_emis_paid_late_src[0],
+        late_fees_paid_by_borrower: u64::from_le_bytes(*late_fees_paid_by_borrower_src),
+        loan_status: loan_status_src[0],
+        guarantor_repaid_amount: u64::from_le_bytes(*guarantor_repaid_amount_src),
+        guarantor_stake_locked_amount: u64::from_le_bytes(*guarantor_stake_locked_amount_src),
+        loan_nonce: u64::from_le_bytes(*loan_nonce_src),
+        lenders_pending_credit_amount: u64::from_le_bytes(*lenders_pending_credit_amount_src),
+        lenders_pending_earning_amount: u64::from_le_bytes(*lenders_pending_earning_amount_src),
+        lenders_round_credit_amount: u64::from_le_bytes(*lenders_round_credit_amount_src),
+        lenders_round_earning_amount: u64::from_le_bytes(*lenders_round_earning_amount_src),
+        next_index_to_credit: next_index_to_credit_src[0],
+        loan_mint: Pubkey::new_from_array(*loan_mint_src),
+        loan_mint_decimals: loan_mint_decimals_src[0],
     })
 }
 
      
 pub fn unpack_unchecked_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccDataHeader, ProgramError> {
-    const LOAN_INFO_HEADER_DATA_BYTES: usize = 116;
     if input.len() != LOAN_INFO_HEADER_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
     }
@@ -225,7 +853,29 @@ pub fn unpack_unchecked_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccD
         next_index_to_store_lender_data_src,
         next_index_to_store_repayment_info_src,
         number_of_emis_needed_to_repay_the_loan_src,
-    ) = array_refs![input, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1];
+        next_index_to_return_funds_src,
+        next_index_to_receive_dust_src,
+        annual_interest_rate_bps_src,
+        amortization_type_src,
+        principal_repaid_by_borrower_src,
+        interest_paid_by_borrower_src,
+        repayment_interval_type_src,
+        emi_interval_days_src,
+        number_of_emis_paid_on_time_src,
+        number_of_emis_paid_late_src,
+        late_fees_paid_by_borrower_src,
+        loan_status_src,
+        guarantor_repaid_amount_src,
+        guarantor_stake_locked_amount_src,
+        loan_nonce_src,
+        lenders_pending_credit_amount_src,
+        lenders_pending_earning_amount_src,
+        lenders_round_credit_amount_src,
+        lenders_round_earning_amount_src,
+        next_index_to_credit_src,
+        loan_mint_src,
+        loan_mint_decimals_src,
+    ) = array_refs![input, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1, 1, 1, 2, 1, 8, 8, 1, 2, 1, 1, 8, 1, 8, 8, 8, 8, 8, 8, 8, 1, 32, 1];
 /*
     if acc_type_src[0] != AccTypes::LoanInfoAcc as u8 {
         return Err(DassiError::ExpectedAccountTypeMismatched.into());
@@ -248,6 +898,28 @@ pub fn unpack_unchecked_to_loan_info_header(input: &[u8]) -> Result<LoanInfoAccD
         next_index_to_store_lender_data: next_index_to_store_lender_data_src[0],
         next_index_to_store_repayment_info: next_index_to_store_repayment_info_src[0],
         number_of_emis_needed_to_repay_the_loan: number_of_emis_needed_to_repay_the_loan_src[0],
+        next_index_to_return_funds: next_index_to_return_funds_src[0],
+        next_index_to_receive_dust: next_index_to_receive_dust_src[0],
+        annual_interest_rate_bps: u16::from_le_bytes(*annual_interest_rate_bps_src),
+        amortization_type: amortization_type_src[0],
+        principal_repaid_by_borrower: u64::from_le_bytes(*principal_repaid_by_borrower_src),
+        interest_paid_by_borrower: u64::from_le_bytes(*interest_paid_by_borrower_src),
+        repayment_interval_type: repayment_interval_type_src[0],
+        emi_interval_days: u16::from_le_bytes(*emi_interval_days_src),
+        number_of_emis_paid_on_time: number_of_emis_paid_on_time_src[0],
+        number_of_emis_paid_late: number_of_emis_paid_late_src[0],
+        late_fees_paid_by_borrower: u64::from_le_bytes(*late_fees_paid_by_borrower_src),
+        loan_status: loan_status_src[0],
+        guarantor_repaid_amount: u64::from_le_bytes(*guarantor_repaid_amount_src),
+        guarantor_stake_locked_amount: u64::from_le_bytes(*guarantor_stake_locked_amount_src),
+        loan_nonce: u64::from_le_bytes(*loan_nonce_src),
+        lenders_pending_credit_amount: u64::from_le_bytes(*lenders_pending_credit_amount_src),
+        lenders_pending_earning_amount: u64::from_le_bytes(*lenders_pending_earning_amount_src),
+        lenders_round_credit_amount: u64::from_le_bytes(*lenders_round_credit_amount_src),
+        lenders_round_earning_amount: u64::from_le_bytes(*lenders_round_earning_amount_src),
+        next_index_to_credit: next_index_to_credit_src[0],
+        loan_mint: Pubkey::new_from_array(*loan_mint_src),
+        loan_mint_decimals: loan_mint_decimals_src[0],
     })
 }
 
@@ -255,7 +927,6 @@ pub fn pack_to_loan_info_header(
     src: LoanInfoAccDataHeader,
     dst: &mut [u8],
 ) -> Result<(), ProgramError> {
-    const LOAN_INFO_HEADER_DATA_BYTES: usize = 116;
     if dst.len() != LOAN_INFO_HEADER_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
     }
@@ -274,7 +945,29 @@ pub fn pack_to_loan_info_header(
         next_index_to_store_lender_data_dst,
         next_index_to_store_repayment_info_dst,
         number_of_emis_needed_to_repay_the_loan_dst,
-    ) = mut_array_refs![dst, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1];
+        next_index_to_return_funds_dst,
+        next_index_to_receive_dust_dst,
+        annual_interest_rate_bps_dst,
+        amortization_type_dst,
+        principal_repaid_by_borrower_dst,
+        interest_paid_by_borrower_dst,
+        repayment_interval_type_dst,
+        emi_interval_days_dst,
+        number_of_emis_paid_on_time_dst,
+        number_of_emis_paid_late_dst,
+        late_fees_paid_by_borrower_dst,
+        loan_status_dst,
+        guarantor_repaid_amount_dst,
+        guarantor_stake_locked_amount_dst,
+        loan_nonce_dst,
+        lenders_pending_credit_amount_dst,
+        lenders_pending_earning_amount_dst,
+        lenders_round_credit_amount_dst,
+        lenders_round_earning_amount_dst,
+        next_index_to_credit_dst,
+        loan_mint_dst,
+        loan_mint_decimals_dst,
+    ) = mut_array_refs![dst, 1, 32, 32, 8, 8, 8, 8, 8, 8, 1, 1, 1, 1, 1, 2, 1, 8, 8, 1, 2, 1, 1, 8, 1, 8, 8, 8, 8, 8, 8, 8, 1, 32, 1];
     let LoanInfoAccDataHeader {
         acc_type,
         borrower_main_acc_pubkey,
@@ -288,6 +981,28 @@ pub fn pack_to_loan_info_header(
         next_index_to_store_lender_data,
         next_index_to_store_repayment_info,
         number_of_emis_needed_to_repay_the_loan,
+        next_index_to_return_funds,
+        next_index_to_receive_dust,
+        annual_interest_rate_bps,
+        amortization_type,
+        principal_repaid_by_borrower,
+        interest_paid_by_borrower,
+        repayment_interval_type,
+        emi_interval_days,
+        number_of_emis_paid_on_time,
+        number_of_emis_paid_late,
+        late_fees_paid_by_borrower,
+        loan_status,
+        guarantor_repaid_amount,
+        guarantor_stake_locked_amount,
+        loan_nonce,
+        lenders_pending_credit_amount,
+        lenders_pending_earning_amount,
+        lenders_round_credit_amount,
+        lenders_round_earning_amount,
+        next_index_to_credit,
+        loan_mint,
+        loan_mint_decimals,
     } = src;
     acc_type_dst[0] = acc_type;
     borrower_main_acc_pubkey_dst.copy_from_slice(borrower_main_acc_pubkey.as_ref());
@@ -301,40 +1016,54 @@ pub fn pack_to_loan_info_header(
     next_index_to_store_lender_data_dst[0] = next_index_to_store_lender_data;
     next_index_to_store_repayment_info_dst[0] = next_index_to_store_repayment_info;
     number_of_emis_needed_to_repay_the_loan_dst[0] = number_of_emis_needed_to_repay_the_loan;
+    next_index_to_return_funds_dst[0] = next_index_to_return_funds;
+    next_index_to_receive_dust_dst[0] = next_index_to_receive_dust;
+    *annual_interest_rate_bps_dst = annual_interest_rate_bps.to_le_bytes();
+    amortization_type_dst[0] = amortization_type;
+    *principal_repaid_by_borrower_dst = principal_repaid_by_borrower.to_le_bytes();
+    *interest_paid_by_borrower_dst = interest_paid_by_borrower.to_le_bytes();
+    repayment_interval_type_dst[0] = repayment_interval_type;
+    *emi_interval_days_dst = emi_interval_days.to_le_bytes();
+    number_of_emis_paid_on_time_dst[0] = number_of_emis_paid_on_time;
+    number_of_emis_paid_late_dst[0] = number_of_emis_paid_late;
+    *late_fees_paid_by_borrower_dst = late_fees_paid_by_borrower.to_le_bytes();
+    loan_status_dst[0] = loan_status;
+    *guarantor_repaid_amount_dst = guarantor_repaid_amount.to_le_bytes();
+    *guarantor_stake_locked_amount_dst = guarantor_stake_locked_amount.to_le_bytes();
+    *loan_nonce_dst = loan_nonce.to_le_bytes();
+    *lenders_pending_credit_amount_dst = lenders_pending_credit_amount.to_le_bytes();
+    *lenders_pending_earning_amount_dst = lenders_pending_earning_amount.to_le_bytes();
+    *lenders_round_credit_amount_dst = lenders_round_credit_amount.to_le_bytes();
+    *lenders_round_earning_amount_dst = lenders_round_earning_amount.to_le_bytes();
+    next_index_to_credit_dst[0] = next_index_to_credit;
+    loan_mint_dst.copy_from_slice(loan_mint.as_ref());
+    loan_mint_decimals_dst[0] = loan_mint_decimals;
     Ok(())
 }
 
-// each LoanInfoAccLendersData takes 45 bytes
-// lenders can lend in multiples of 10 DassiCoin for prototype
+// each LoanInfoAccLendersData takes 40 bytes
+// lenders can lend in multiples of 10 tokens of loan mint for prototype
 // Borrowers can borrow in multiples of 100 DassiCoin for prototype
 // For prototype borrowers can borrow a loan amount between 200 DassiCoin to 2000 DassiCoin
-// So, total bytes needed to store LoanInfoAccLendersData 45*(2000/10) = 9000 bytes
+// So, total bytes needed to store LoanInfoAccLendersData 40*(2000/10) = 8000 bytes
+pub const LENDING_AMOUNT_UNIT_TOKENS: u64 = 10;
+pub const MAX_NUMBER_OF_LENDERS_PER_LOAN: u8 = 200;
 pub struct LoanInfoAccLendersData {
     pub lender_main_acc_pubkey: Pubkey,
-    pub lenders_data_storage_acc_number: u8,
-    pub lender_id: u32,
     pub lent_amount: u64,
 }
 
 pub fn unpack_to_loan_info_acc_lender_data(
     input: &[u8],
 ) -> Result<LoanInfoAccLendersData, ProgramError> {
-    const LOAN_INFO_ACC_LENDER_DATA_BYTES: usize = 45;
     if input.len() != LOAN_INFO_ACC_LENDER_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
     }
     let input = array_ref![input, 0, LOAN_INFO_ACC_LENDER_DATA_BYTES];
-    let (
-        lender_main_acc_pubkey_src,
-        lenders_data_storage_acc_number_src,
-        lender_id_src,
-        lent_amount_src,
-    ) = array_refs![input, 32, 1, 4, 8];
+    let (lender_main_acc_pubkey_src, lent_amount_src) = array_refs![input, 32, 8];
 
     Ok(LoanInfoAccLendersData {
         lender_main_acc_pubkey: Pubkey::new_from_array(*lender_main_acc_pubkey_src),
-        lenders_data_storage_acc_number: lenders_data_storage_acc_number_src[0],
-        lender_id: u32::from_le_bytes(*lender_id_src),
         lent_amount: u64::from_le_bytes(*lent_amount_src),
     })
 }
@@ -343,37 +1072,91 @@ pub fn pack_to_loan_info_acc_lender_data(
     src: LoanInfoAccLendersData,
     dst: &mut [u8],
 ) -> Result<(), ProgramError> {
-    const LOAN_INFO_ACC_LENDER_DATA_BYTES: usize = 45;
     if dst.len() != LOAN_INFO_ACC_LENDER_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
     }
 
     let dst = array_mut_ref![dst, 0, LOAN_INFO_ACC_LENDER_DATA_BYTES];
-    let (
-        lender_main_acc_pubkey_dst,
-        lenders_data_storage_acc_number_dst,
-        lender_id_dst,
-        lent_amount_dst,
-    ) = mut_array_refs![dst, 32, 1, 4, 8];
+    let (lender_main_acc_pubkey_dst, lent_amount_dst) = mut_array_refs![dst, 32, 8];
     let LoanInfoAccLendersData {
         lender_main_acc_pubkey,
-        lenders_data_storage_acc_number,
-        lender_id,
         lent_amount,
     } = src;
     lender_main_acc_pubkey_dst.copy_from_slice(lender_main_acc_pubkey.as_ref());
-    lenders_data_storage_acc_number_dst[0] = lenders_data_storage_acc_number;
-    *lender_id_dst = lender_id.to_le_bytes();
     *lent_amount_dst = lent_amount.to_le_bytes();
     Ok(())
 }
 
 
-// each LoanInfoAccRepaymentData take 16 bytes
-// a borrower can choose to pay a loan in maximum 50 emis, so LoanInfoAccRepaymentsData will take 16*50 = 800 bytes
+// each LoanInfoAccRepaymentData take 25 bytes, one LoanInfoAccRepaymentData is stored for every emi of the schedule
+// a borrower can choose to pay a loan in maximum 36 emis, so LoanInfoAccRepaymentsData will take 25*36 = 900 bytes
+// and whole loan info account stays below 10KB
+pub const MAX_NUMBER_OF_EMIS: u8 = 36;
+pub enum EmiStatus {
+    NotPaid = 0,
+    PartiallyPaid = 1,
+    PaidOnTime = 2,
+    PaidLate = 3,
+}
+
 pub struct LoanInfoAccRepaymentData {
+    // timestamp of last payment towards this emi
     pub emi_repayment_timestamp: u64,
+    // emi_repayment_amount includes emi_interest_amount, principal part is emi_repayment_amount - emi_interest_amount
     pub emi_repayment_amount: u64,
+    pub emi_interest_amount: u64,
+    // EmiStatus as u8
+    pub emi_status: u8,
+}
+
+pub fn unpack_to_loan_info_acc_repayment_data(
+    input: &[u8],
+) -> Result<LoanInfoAccRepaymentData, ProgramError> {
+    if input.len() != LOAN_INFO_ACC_REPAYMENT_DATA_BYTES {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let input = array_ref![input, 0, LOAN_INFO_ACC_REPAYMENT_DATA_BYTES];
+    let (
+        emi_repayment_timestamp_src,
+        emi_repayment_amount_src,
+        emi_interest_amount_src,
+        emi_status_src,
+    ) = array_refs![input, 8, 8, 8, 1];
+
+    Ok(LoanInfoAccRepaymentData {
+        emi_repayment_timestamp: u64::from_le_bytes(*emi_repayment_timestamp_src),
+        emi_repayment_amount: u64::from_le_bytes(*emi_repayment_amount_src),
+        emi_interest_amount: u64::from_le_bytes(*emi_interest_amount_src),
+        emi_status: emi_status_src[0],
+    })
+}
+
+pub fn pack_to_loan_info_acc_repayment_data(
+    src: LoanInfoAccRepaymentData,
+    dst: &mut [u8],
+) -> Result<(), ProgramError> {
+    if dst.len() != LOAN_INFO_ACC_REPAYMENT_DATA_BYTES {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let dst = array_mut_ref![dst, 0, LOAN_INFO_ACC_REPAYMENT_DATA_BYTES];
+    let (
+        emi_repayment_timestamp_dst,
+        emi_repayment_amount_dst,
+        emi_interest_amount_dst,
+        emi_status_dst,
+    ) = mut_array_refs![dst, 8, 8, 8, 1];
+    let LoanInfoAccRepaymentData {
+        emi_repayment_timestamp,
+        emi_repayment_amount,
+        emi_interest_amount,
+        emi_status,
+    } = src;
+    *emi_repayment_timestamp_dst = emi_repayment_timestamp.to_le_bytes();
+    *emi_repayment_amount_dst = emi_repayment_amount.to_le_bytes();
+    *emi_interest_amount_dst = emi_interest_amount.to_le_bytes();
+    emi_status_dst[0] = emi_status;
+    Ok(())
 }
 
 /*
@@ -387,11 +1170,12 @@ pub struct LoanInfoAccData {
 
 */
 
-pub const LOAN_INFO_HEADER_DATA_BYTES: usize = 116;
-pub const LOAN_INFO_ACC_LENDER_DATA_BYTES: usize = 45;
-pub const LOAN_INFO_LENDERS_DATA_BYTES: usize = 9000;
-pub const LOAN_INFO_REPAYMENT_DATA_BYTES: usize = 800;
-// total bytes needed to store LoanInfoAccData = 116 + 9000 + 800 = 9916
+pub const LOAN_INFO_HEADER_DATA_BYTES: usize = 241;
+pub const LOAN_INFO_ACC_LENDER_DATA_BYTES: usize = 40;
+pub const LOAN_INFO_ACC_REPAYMENT_DATA_BYTES: usize = 25;
+pub const LOAN_INFO_LENDERS_DATA_BYTES: usize = 8000;
+pub const LOAN_INFO_REPAYMENT_DATA_BYTES: usize = 900;
+// total bytes needed to store LoanInfoAccData = 241 + 8000 + 900 = 9141
 pub const LOAN_INFO_DATA_TOTAL_BYTES: usize =
     LOAN_INFO_HEADER_DATA_BYTES + LOAN_INFO_LENDERS_DATA_BYTES + LOAN_INFO_REPAYMENT_DATA_BYTES;
 pub const LOAN_INFO_ACC_DATA_TYPE_INDEX: usize = 0;
@@ -402,6 +1186,7 @@ pub const PUBKEY_DATA_BYTES: usize = 32;
 pub const TIMESTAMP_DATA_BYTES: usize = 8;
 pub const AMOUNT_DATA_BYTES: usize = 8;
 pub const U8_DATA_BYTES: usize = 1;
+pub const U16_DATA_BYTES: usize = 2;
 pub const LOAN_INFO_BORROWER_MAIN_ACCOUNT_START_INDEX: usize = LOAN_INFO_HEADER_START_INDEX;
 pub const LOAN_INFO_BORROWER_MAIN_ACCOUNT_END_INDEX: usize =
     LOAN_INFO_BORROWER_MAIN_ACCOUNT_START_INDEX + PUBKEY_DATA_BYTES;
@@ -425,6 +1210,50 @@ pub const LOAN_INFO_NEXT_INDEX_TO_STORE_REPAYMENT_DATA: usize =
     LOAN_INFO_NEXT_INDEX_TO_STORE_LENDER_DATA + U8_DATA_BYTES;
 pub const LOAN_INFO_NUMBER_EMIS_NEEDED_TO_REPAY_LOAN_EI: usize =
     LOAN_INFO_NEXT_INDEX_TO_STORE_REPAYMENT_DATA + U8_DATA_BYTES;
+pub const LOAN_INFO_NEXT_INDEX_TO_RETURN_FUNDS_EI: usize =
+    LOAN_INFO_NUMBER_EMIS_NEEDED_TO_REPAY_LOAN_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_NEXT_INDEX_TO_RECEIVE_DUST_EI: usize =
+    LOAN_INFO_NEXT_INDEX_TO_RETURN_FUNDS_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_ANNUAL_INTEREST_RATE_BPS_EI: usize =
+    LOAN_INFO_NEXT_INDEX_TO_RECEIVE_DUST_EI + U16_DATA_BYTES;
+pub const LOAN_INFO_AMORTIZATION_TYPE_EI: usize =
+    LOAN_INFO_ANNUAL_INTEREST_RATE_BPS_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_PRINCIPAL_REPAID_BY_BORROWER_EI: usize =
+    LOAN_INFO_AMORTIZATION_TYPE_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_INTEREST_PAID_BY_BORROWER_EI: usize =
+    LOAN_INFO_PRINCIPAL_REPAID_BY_BORROWER_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_REPAYMENT_INTERVAL_TYPE_EI: usize =
+    LOAN_INFO_INTEREST_PAID_BY_BORROWER_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_EMI_INTERVAL_DAYS_EI: usize =
+    LOAN_INFO_REPAYMENT_INTERVAL_TYPE_EI + U16_DATA_BYTES;
+pub const LOAN_INFO_NUMBER_OF_EMIS_PAID_ON_TIME_EI: usize =
+    LOAN_INFO_EMI_INTERVAL_DAYS_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_NUMBER_OF_EMIS_PAID_LATE_EI: usize =
+    LOAN_INFO_NUMBER_OF_EMIS_PAID_ON_TIME_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_LATE_FEES_PAID_BY_BORROWER_EI: usize =
+    LOAN_INFO_NUMBER_OF_EMIS_PAID_LATE_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LOAN_STATUS_EI: usize =
+    LOAN_INFO_LATE_FEES_PAID_BY_BORROWER_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_GUARANTOR_REPAID_AMOUNT_EI: usize =
+    LOAN_INFO_LOAN_STATUS_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_GUARANTOR_STAKE_LOCKED_AMOUNT_EI: usize =
+    LOAN_INFO_GUARANTOR_REPAID_AMOUNT_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LOAN_NONCE_EI: usize =
+    LOAN_INFO_GUARANTOR_STAKE_LOCKED_AMOUNT_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LENDERS_PENDING_CREDIT_AMOUNT_EI: usize =
+    LOAN_INFO_LOAN_NONCE_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LENDERS_PENDING_EARNING_AMOUNT_EI: usize =
+    LOAN_INFO_LENDERS_PENDING_CREDIT_AMOUNT_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LENDERS_ROUND_CREDIT_AMOUNT_EI: usize =
+    LOAN_INFO_LENDERS_PENDING_EARNING_AMOUNT_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_LENDERS_ROUND_EARNING_AMOUNT_EI: usize =
+    LOAN_INFO_LENDERS_ROUND_CREDIT_AMOUNT_EI + AMOUNT_DATA_BYTES;
+pub const LOAN_INFO_NEXT_INDEX_TO_CREDIT_EI: usize =
+    LOAN_INFO_LENDERS_ROUND_EARNING_AMOUNT_EI + U8_DATA_BYTES;
+pub const LOAN_INFO_LOAN_MINT_EI: usize =
+    LOAN_INFO_NEXT_INDEX_TO_CREDIT_EI + PUBKEY_DATA_BYTES;
+pub const LOAN_INFO_LOAN_MINT_DECIMALS_EI: usize =
+    LOAN_INFO_LOAN_MINT_EI + U8_DATA_BYTES;
 
 pub const LOAN_INFO_ACC_LENDERS_DATA_START_INDEX: usize = LOAN_INFO_HEADER_DATA_BYTES;
 pub const LOAN_INFO_ACC_LENDERS_DATA_END_INDEX: usize =
@@ -433,17 +1262,19 @@ pub const LOAN_INFO_ACC_REPAYMENT_DATA_SI: usize = LOAN_INFO_ACC_LENDERS_DATA_EN
 pub const LOAN_INFO_ACC_REPAYMENT_DATA_EI: usize =
     LOAN_INFO_ACC_LENDERS_DATA_END_INDEX + LOAN_INFO_REPAYMENT_DATA_BYTES;
 
-// total bytes needed to store LoanInfoAccData = 116 + 9000 + 800 = 9916
+// total bytes needed to store LoanInfoAccData = 241 + 8000 + 900 = 9141
 
-// If we take size of Lenders_data_storage_acc to be 10Mb, then it can accomodate 153_846 lenders data as each lender data consumes 65 bytes
-// So if in future we have a lot of users (for ex 1.6 Million lenders) then we will generate 10 Lenders_data_storage_acc address each of 10Mb,
-// and we will pass all 10 accounts for every program call, and then in our program lenders_data_storage_acc_number will tell us in which account to look for a lender with lender_id
-// lenders_data_storage_acc_number stores numbers from 1,2,3...
+// Legacy lenders storage, lenders are now stored in LenderAccount pdas. MigrateLenderAccount moves every active slot of it
+// to the lender's pda, so below layout is only read by migration. It is the layout of the deployed account and must not change,
+// new lender fields go to LenderAccount.
+// Lenders storage is not sharded across accounts by lenders_data_storage_acc_number: only account number 1 was ever created
+// and migration rejects any other. Every lender has his own LenderAccount pda now, so there is no limit on number of lenders
+// for sharding to lift
 
 // for prototype I will use 3.25 Mb for Lenders_data_storage_acc, so it will accomodate 50_000 lenders data
 // Total account size for LendersAccountData = 3_250_002 bytes
 // total_lending_amount can be act as lending_score
-pub struct LenderAccountData {
+pub struct LegacyLenderAccountData {
     pub is_account_active: u8,
     pub lender_main_acc_pubkey: Pubkey,
     pub total_lending_amount: u128,
@@ -451,9 +1282,9 @@ pub struct LenderAccountData {
     pub amount_to_withdraw_or_lend: u64,
 }
 
-pub fn unpack_to_lender_account_data(
+pub fn unpack_to_legacy_lender_account_data(
     input: &[u8],
-) -> Result<LenderAccountData, ProgramError> {
+) -> Result<LegacyLenderAccountData, ProgramError> {
     const LENDER_ACCOUNT_DATA_BYTES: usize = 65;
     if input.len() != LENDER_ACCOUNT_DATA_BYTES {
         return Err(ProgramError::InvalidAccountData);
@@ -467,7 +1298,7 @@ pub fn unpack_to_lender_account_data(
         amount_to_withdraw_or_lend_src,
     ) = array_refs![input, 1, 32, 16, 8, 8];
 
-    Ok(LenderAccountData {
+    Ok(LegacyLenderAccountData {
         is_account_active: is_account_active_src[0],
         lender_main_acc_pubkey: Pubkey::new_from_array(*lender_main_acc_pubkey_src),
         total_lending_amount: u128::from_le_bytes(*total_lending_amount_src),
@@ -476,8 +1307,8 @@ pub fn unpack_to_lender_account_data(
     })
 }
 
-pub fn pack_to_lender_account_data(
-    src: LenderAccountData,
+pub fn pack_to_legacy_lender_account_data(
+    src: LegacyLenderAccountData,
     dst: &mut [u8],
 ) -> Result<(), ProgramError> {
     const LENDER_ACCOUNT_DATA_BYTES: usize = 65;
@@ -493,7 +1324,7 @@ pub fn pack_to_lender_account_data(
         total_unique_lending_amount_dst,
         amount_to_withdraw_or_lend_dst,
     ) = mut_array_refs![dst, 1, 32, 16, 8, 8];
-    let LenderAccountData {
+    let LegacyLenderAccountData {
         is_account_active,
         lender_main_acc_pubkey,
         total_lending_amount,
@@ -512,11 +1343,14 @@ pub fn pack_to_lender_account_data(
 
 
 pub const LENDERS_ACC_DATA_TYPE_INDEX: usize = 0;
-pub const LENDER_ACC_DATA_SIZE: usize = 65;
+pub const LEGACY_LENDER_ACC_DATA_SIZE: usize = 65;
+// 50_000 lenders fit in lenders storage account after its 2 header bytes
+pub const LEGACY_MAX_NUMBER_OF_LENDERS: usize = (LEGACY_LENDERS_STORAGE_ACC_DATA_SIZE - 2) / LEGACY_LENDER_ACC_DATA_SIZE;
 pub const LENDERS_ACC_DATA_STARTING_INDEX: usize = 1;
 
-pub struct LendersAccountDataArray {
+pub struct LegacyLendersAccountDataArray {
     pub acc_type: u8,
+    // always 1, see comment on LegacyLenderAccountData
     pub lenders_data_storage_acc_number: u8,
-    pub lenders_acc_array_data: [LenderAccountData],
+    pub lenders_acc_array_data: [LegacyLenderAccountData],
 }
@@ -11,11 +11,135 @@ pub enum AccTypes {
 // AccTypes::LenderAcc as u8
 
 
+// 857Tm9dNi6Ypur9zCcJ9oAhqYd3bE6J6s2ww77PKCSa, only this key can create protocol config. Once config exists admin is read from
+// it, so this key is used just once and admin can be handed over later with ProposeAdmin and AcceptAdmin
+const ADMIN_PUBKEY_BYTES: [u8; 32] = [
+    1, 207, 182, 229, 175, 113, 23, 229, 156, 90, 244, 106, 153, 132, 56, 193, 178, 32, 49, 224, 109, 140, 223, 122, 225,
+    255, 167, 208, 39, 137, 218, 199,
+];
+
 pub fn get_admin_pubkey() -> Pubkey {
-    let admin_pubkey_str = "857Tm9dNi6Ypur9zCcJ9oAhqYd3bE6J6s2ww77PKCSa";
-    let pubkey_vec = bs58::decode(admin_pubkey_str).into_vec().unwrap();
-    let admin_pubkey = Pubkey::new(&pubkey_vec);
-    return admin_pubkey;
+    Pubkey::new_from_array(ADMIN_PUBKEY_BYTES)
+}
+
+pub fn get_protocol_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"DassiFinanceConfig"], program_id)
+}
+
+pub fn get_borrower_storage_address(borrower_main_pubkey: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"DassiFinanceBorrower", borrower_main_pubkey.as_ref()], program_id)
+}
+
+pub fn get_guarantor_storage_address(
+    guarantor_main_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"DassiFinanceGuarantor", guarantor_main_pubkey.as_ref()], program_id)
+}
+
+pub fn get_lender_storage_address(
+    lender_main_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DassiFinanceLender", lender_main_pubkey.as_ref(), mint_pubkey.as_ref()],
+        program_id,
+    )
+}
+
+pub fn get_lender_position_address(
+    lender_main_pubkey: &Pubkey,
+    loan_info_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DassiFinancePosition", lender_main_pubkey.as_ref(), loan_info_pubkey.as_ref()],
+        program_id,
+    )
+}
+
+pub fn get_loan_info_address(
+    borrower_main_pubkey: &Pubkey,
+    loan_nonce: u64,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DassiFinanceLoan", borrower_main_pubkey.as_ref(), &loan_nonce.to_le_bytes()],
+        program_id,
+    )
+}
+
+// vault holding loan funds of every loan in a mint
+pub fn get_mint_vault_address(mint_pubkey: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"DassiFinanceVault", mint_pubkey.as_ref()], program_id)
+}
+
+pub fn get_guarantor_stake_address(
+    guarantor_main_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DassiFinanceGuarantorStake", guarantor_main_pubkey.as_ref(), mint_pubkey.as_ref()],
+        program_id,
+    )
+}
+
+// vault holding tokens staked in a guarantor stake account
+pub fn get_guarantor_stake_vault_address(
+    guarantor_stake_account_key: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DassiFinanceGuarantorStakeVault", guarantor_stake_account_key.as_ref()],
+        program_id,
+    )
+}
+
+// converts amount in base units of a mint with from_decimals to base units of a mint with to_decimals, rounding down
+pub fn convert_amount_decimals(amount: u64, from_decimals: u8, to_decimals: u8) -> Option<u64> {
+    if to_decimals >= from_decimals {
+        amount.checked_mul(10u64.checked_pow((to_decimals - from_decimals) as u32)?)
+    } else {
+        amount.checked_div(10u64.checked_pow((from_decimals - to_decimals) as u32)?)
+    }
+}
+
+// Splits amount between lenders in proportion to their lent amounts. Rounding down every share leaves some dust which is always
+// less than number of lenders, so dust is given 1 token unit per lender starting from dust_start_index and wrapping around.
+// Returned index is where next distribution should start giving dust, so over many repayments every lender gets equal dust
+pub fn distribute_pro_rata(
+    amount: u64,
+    lent_amounts: &[u64],
+    dust_start_index: usize,
+) -> Option<(Vec<u64>, usize)> {
+    let num_lenders = lent_amounts.len();
+    let total_amount_lended: u128 = lent_amounts.iter().map(|x| *x as u128).sum();
+    if total_amount_lended == 0 {
+        return None;
+    }
+
+    let mut shares: Vec<u64> = Vec::with_capacity(num_lenders);
+    let mut distributed_amount: u64 = 0;
+    for lent_amount in lent_amounts {
+        let share: u64 = (amount as u128)
+            .checked_mul(*lent_amount as u128)?
+            .checked_div(total_amount_lended)?
+            .try_into()
+            .ok()?;
+        distributed_amount = distributed_amount.checked_add(share)?;
+        shares.push(share);
+    }
+
+    let dust = amount.checked_sub(distributed_amount)? as usize;
+    let dust_start_index = dust_start_index % num_lenders;
+    for i in 0..dust {
+        let index = (dust_start_index + i) % num_lenders;
+        shares[index] = shares[index].checked_add(1)?;
+    }
+
+    Some((shares, (dust_start_index + dust) % num_lenders))
 }
 
 
@@ -47,4 +171,52 @@ let borrower_storage_account = next_account_info(account_info_iter)?;
         borrower_data.active_loan_address = *loan_info_storage_account.key;
         BorrowerAccount::pack(borrower_data, &mut borrower_storage_account.data.try_borrow_mut().unwrap())?;
 
-        */
\ No newline at end of file
+        */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pro_rata_shares_sum_to_amount() {
+        let lent_amounts_list: [&[u64]; 4] = [
+            &[1],
+            &[3, 3, 3],
+            &[7, 11, 13, 1_000_000],
+            &[u64::MAX, 1, u64::MAX / 3],
+        ];
+        for lent_amounts in lent_amounts_list.iter() {
+            for amount in [0u64, 1, 10, 999_999, u64::MAX].iter() {
+                let (shares, _) = distribute_pro_rata(*amount, lent_amounts, 0).unwrap();
+                assert_eq!(shares.len(), lent_amounts.len());
+                assert_eq!(shares.iter().map(|x| *x as u128).sum::<u128>(), *amount as u128);
+
+                // dust is less than number of lenders, so no lender gets more than 1 token unit of it
+                let total_amount_lended: u128 = lent_amounts.iter().map(|x| *x as u128).sum();
+                let mut dust: u64 = 0;
+                for (share, lent_amount) in shares.iter().zip(lent_amounts.iter()) {
+                    let rounded_down_share =
+                        (*amount as u128 * *lent_amount as u128 / total_amount_lended) as u64;
+                    assert!(*share - rounded_down_share <= 1);
+                    dust += *share - rounded_down_share;
+                }
+                assert!((dust as usize) < lent_amounts.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pro_rata_dust_index_wraps_around() {
+        // every share rounds down to 1 and 2 units of dust go to lenders 2 and 0
+        assert_eq!(distribute_pro_rata(5, &[1, 1, 1], 2), Some((vec![2, 1, 2], 1)));
+        // start index past number of lenders wraps around too
+        assert_eq!(distribute_pro_rata(5, &[1, 1, 1], 5), Some((vec![2, 1, 2], 1)));
+        assert_eq!(distribute_pro_rata(6, &[1, 1, 1], 2), Some((vec![2, 2, 2], 2)));
+    }
+
+    #[test]
+    fn test_pro_rata_without_lent_amount() {
+        assert_eq!(distribute_pro_rata(100, &[0, 0], 0), None);
+        assert_eq!(distribute_pro_rata(100, &[], 0), None);
+    }
+}
@@ -0,0 +1,226 @@
+// Account checks shared by processor instructions. Borrower, guarantor, lender, lender position and loan storage accounts must be owned by the program
+// and sit at their pda. Token account checks return unpacked account data, so callers can take balance before transfer from
+// it without unpacking the account again.
+
+use crate::{
+    error::DassiError,
+    state::{LoanInfoAccDataHeader, ProtocolConfig},
+    utils,
+};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+fn check_program_account(
+    account: &AccountInfo,
+    expected_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if account.owner != program_id {
+        return Err(DassiError::WrongAccountPassed.into());
+    }
+    if account.key != expected_pubkey {
+        return Err(DassiError::AccountMismatched.into());
+    }
+    Ok(())
+}
+
+pub fn check_borrower_storage_account(
+    borrower_storage_account: &AccountInfo,
+    borrower_main_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) =
+        utils::get_borrower_storage_address(borrower_main_pubkey, program_id);
+    check_program_account(borrower_storage_account, &expected_pubkey, program_id)
+}
+
+pub fn check_guarantor_storage_account(
+    guarantor_storage_account: &AccountInfo,
+    guarantor_main_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) =
+        utils::get_guarantor_storage_address(guarantor_main_pubkey, program_id);
+    check_program_account(guarantor_storage_account, &expected_pubkey, program_id)
+}
+
+pub fn check_lender_storage_account(
+    lender_storage_account: &AccountInfo,
+    lender_main_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) =
+        utils::get_lender_storage_address(lender_main_pubkey, mint_pubkey, program_id);
+    check_program_account(lender_storage_account, &expected_pubkey, program_id)
+}
+
+pub fn check_guarantor_stake_account(
+    guarantor_stake_account: &AccountInfo,
+    guarantor_main_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) =
+        utils::get_guarantor_stake_address(guarantor_main_pubkey, mint_pubkey, program_id);
+    check_program_account(guarantor_stake_account, &expected_pubkey, program_id)
+}
+
+pub fn check_lender_position_account(
+    lender_position_account: &AccountInfo,
+    lender_main_pubkey: &Pubkey,
+    loan_info_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) =
+        utils::get_lender_position_address(lender_main_pubkey, loan_info_pubkey, program_id);
+    check_program_account(lender_position_account, &expected_pubkey, program_id)
+}
+
+// loan account address is derived from borrower and nonce stored in its own header, so header must be unpacked first
+pub fn check_loan_info_account(
+    loan_info_storage_account: &AccountInfo,
+    loan_info_header_data: &LoanInfoAccDataHeader,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_pubkey, _bump_seed) = utils::get_loan_info_address(
+        &loan_info_header_data.borrower_main_acc_pubkey,
+        loan_info_header_data.loan_nonce,
+        program_id,
+    );
+    check_program_account(loan_info_storage_account, &expected_pubkey, program_id)
+}
+
+pub fn check_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if token_program.key != &spl_token::id() {
+        return Err(DassiError::InvalidTokenProgram.into());
+    }
+    Ok(())
+}
+
+// mint account a loan is created in, it must be in accepted loan mints of config
+pub fn unpack_loan_mint(mint_account: &AccountInfo, config: &ProtocolConfig) -> Result<Mint, ProgramError> {
+    if !config.is_accepted_loan_mint(mint_account.key) {
+        return Err(DassiError::LoanMintNotAccepted.into());
+    }
+    if mint_account.owner != &spl_token::id() {
+        return Err(DassiError::MintDoesNotMatched.into());
+    }
+    Mint::unpack(&mint_account.data.borrow())
+}
+
+// mint account admin adds to accepted loan mints of config, it must not be accepted already
+pub fn unpack_new_loan_mint(mint_account: &AccountInfo, config: &ProtocolConfig) -> Result<Mint, ProgramError> {
+    if config.is_accepted_loan_mint(mint_account.key) {
+        return Err(DassiError::LoanMintAlreadyAccepted.into());
+    }
+    if mint_account.owner != &spl_token::id() {
+        return Err(DassiError::MintDoesNotMatched.into());
+    }
+    Mint::unpack(&mint_account.data.borrow())
+}
+
+// any token account holding coins of mint, owned by token program
+fn unpack_token_account(
+    token_account: &AccountInfo,
+    mint_pubkey: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    if token_account.owner != &spl_token::id() {
+        return Err(DassiError::InvalidTokenAccount.into());
+    }
+    let token_account_data = TokenAccount::unpack(&token_account.data.borrow())?;
+    if token_account_data.mint != *mint_pubkey {
+        return Err(DassiError::MintDoesNotMatched.into());
+    }
+    Ok(token_account_data)
+}
+
+// vault of a mint, at its pda and owned by "DassiFinance" pda
+pub fn validate_mint_vault(
+    mint_vault_account: &AccountInfo,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let (expected_mint_vault_pubkey, _bump_seed) =
+        utils::get_mint_vault_address(mint_pubkey, program_id);
+    if expected_mint_vault_pubkey != *mint_vault_account.key {
+        return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+    }
+    let vault_data = unpack_token_account(mint_vault_account, mint_pubkey)?;
+    let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+    if vault_data.owner != pda {
+        return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+    }
+    Ok(vault_data)
+}
+
+// DassiCoin vault deployed before mint vaults, an ordinary token account whose owner was set to "DassiFinance" pda with
+// TransferDassiVaultAccountOwnership
+pub fn validate_legacy_vault(
+    legacy_vault_account: &AccountInfo,
+    config: &ProtocolConfig,
+    program_id: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let (mint_vault_pubkey, _bump_seed) =
+        utils::get_mint_vault_address(&config.params.accepted_mint, program_id);
+    if mint_vault_pubkey == *legacy_vault_account.key {
+        return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+    }
+    let vault_data = unpack_token_account(legacy_vault_account, &config.params.accepted_mint)?;
+    let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+    if vault_data.owner != pda {
+        return Err(DassiError::DassiVaultAccountDoesNotMatched.into());
+    }
+    Ok(vault_data)
+}
+
+// airdrop vault, owned by "DassiFinanceAirdrop" pda
+pub fn validate_airdrop_vault(
+    airdrop_vault_account: &AccountInfo,
+    config: &ProtocolConfig,
+    program_id: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let vault_data = unpack_token_account(airdrop_vault_account, &config.params.accepted_mint)?;
+    let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinanceAirdrop"], program_id);
+    if vault_data.owner != pda {
+        return Err(DassiError::AirdropVaultAccountDoesNotMatched.into());
+    }
+    Ok(vault_data)
+}
+
+// stake vault of a guarantor stake account, at its pda and owned by "DassiFinance" pda
+pub fn validate_guarantor_stake_vault(
+    guarantor_stake_vault_account: &AccountInfo,
+    guarantor_stake_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let (expected_guarantor_stake_vault_pubkey, _bump_seed) =
+        utils::get_guarantor_stake_vault_address(guarantor_stake_pubkey, program_id);
+    if expected_guarantor_stake_vault_pubkey != *guarantor_stake_vault_account.key {
+        return Err(DassiError::GuarantorStakeVaultDoesNotMatched.into());
+    }
+    let vault_data = unpack_token_account(guarantor_stake_vault_account, mint_pubkey)?;
+    let (pda, _bump_seed) = Pubkey::find_program_address(&[b"DassiFinance"], program_id);
+    if vault_data.owner != pda {
+        return Err(DassiError::GuarantorStakeVaultDoesNotMatched.into());
+    }
+    Ok(vault_data)
+}
+
+// Token account of a user, funds are debited from or credited to it. It must belong to the user so that
+// withdrawals can't be sent to someone else's account
+pub fn validate_user_token_account(
+    user_token_account: &AccountInfo,
+    user_main_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let token_account_data = unpack_token_account(user_token_account, mint_pubkey)?;
+    if token_account_data.owner != *user_main_pubkey {
+        return Err(DassiError::TokenAccountOwnerDoesNotMatched.into());
+    }
+    Ok(token_account_data)
+}
@@ -0,0 +1,275 @@
+mod common;
+
+use common::{config_data, lender_account_data, set_syscall_stubs, token_account_data};
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::{
+    self, AccTypes, LenderAccount, LenderPositionAccount, LenderPositionStatus,
+    LoanInfoAccLendersData, LoanStatus,
+};
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use spl_token::state::Account as TokenAccount;
+
+const NOW: i64 = 1_700_000_000;
+const LOAN_MINT_DECIMALS: u8 = 6;
+const OTHER_LENDER_LENT_AMOUNT: u64 = 100_000_000;
+// lender's lending was clamped to the 7 tokens loan still needed, so it is not a multiple of lending unit
+const LENT_AMOUNT: u64 = 7_000_000;
+
+fn loan_info_data(
+    borrower_main_pubkey: &Pubkey,
+    loan_mint: &Pubkey,
+    lenders: Vec<LoanInfoAccLendersData>,
+) -> Vec<u8> {
+    let mut data = vec![0u8; state::LOAN_INFO_ACC_DATA_SIZE];
+    let mut loan_info_header_data = state::unpack_unchecked_to_loan_info_header(
+        &data[state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+    )
+    .unwrap();
+    loan_info_header_data.acc_type = AccTypes::LoanInfoAcc as u8;
+    loan_info_header_data.borrower_main_acc_pubkey = *borrower_main_pubkey;
+    loan_info_header_data.fundraising_period_ending_timestamp = (NOW + 86_400) as u64;
+    loan_info_header_data.total_loan_amount = 1_000_000_000;
+    loan_info_header_data.total_amount_lended =
+        lenders.iter().map(|lender| lender.lent_amount).sum();
+    loan_info_header_data.next_index_to_store_lender_data = lenders.len() as u8;
+    loan_info_header_data.loan_status = LoanStatus::Fundraising as u8;
+    loan_info_header_data.loan_mint = *loan_mint;
+    loan_info_header_data.loan_mint_decimals = LOAN_MINT_DECIMALS;
+    state::pack_to_loan_info_header(
+        loan_info_header_data,
+        &mut data[state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+    )
+    .unwrap();
+    for (index, lender) in lenders.into_iter().enumerate() {
+        let si =
+            state::LOAN_INFO_HEADER_DATA_BYTES + index * state::LOAN_INFO_ACC_LENDER_DATA_BYTES;
+        state::pack_to_loan_info_acc_lender_data(
+            lender,
+            &mut data[si..si + state::LOAN_INFO_ACC_LENDER_DATA_BYTES],
+        )
+        .unwrap();
+    }
+    data
+}
+
+fn lender_position_data(lender_main_pubkey: &Pubkey, loan_info_pubkey: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; LenderPositionAccount::LEN];
+    let mut lender_position_data = LenderPositionAccount::unpack_unchecked(&data).unwrap();
+    lender_position_data.is_initialized = true;
+    lender_position_data.acc_type = AccTypes::LenderPositionAcc as u8;
+    lender_position_data.lender_main_acc_pubkey = *lender_main_pubkey;
+    lender_position_data.loan_info_acc_pubkey = *loan_info_pubkey;
+    lender_position_data.principal_lent = LENT_AMOUNT;
+    lender_position_data.position_status = LenderPositionStatus::Active as u8;
+    LenderPositionAccount::pack(lender_position_data, &mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_cancel_whole_slot_not_multiple_of_lending_unit() {
+    set_syscall_stubs(NOW);
+
+    let program_id = Pubkey::new_unique();
+    let loan_mint = Pubkey::new_unique();
+    let borrower_main_pubkey = Pubkey::new_unique();
+    let other_lender_main_pubkey = Pubkey::new_unique();
+
+    let lender_main_key = Pubkey::new_unique();
+    let lender_ata_key = Pubkey::new_unique();
+    let vault_key = utils::get_mint_vault_address(&loan_mint, &program_id).0;
+    let token_program_key = spl_token::id();
+    let loan_info_key = utils::get_loan_info_address(&borrower_main_pubkey, 0, &program_id).0;
+    let lender_storage_key =
+        utils::get_lender_storage_address(&lender_main_key, &loan_mint, &program_id).0;
+    let lender_position_key =
+        utils::get_lender_position_address(&lender_main_key, &loan_info_key, &program_id).0;
+    let pda_key = Pubkey::find_program_address(&[b"DassiFinance"], &program_id).0;
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+    let system_program_key = solana_program::system_program::id();
+
+    let mut lender_main_lamports = 1_000_000_000u64;
+    let mut lender_main_data = vec![];
+    let mut lender_ata_lamports = 1u64;
+    let mut lender_ata_data = token_account_data(&loan_mint, &lender_main_key, 0);
+    let mut vault_lamports = 1u64;
+    let mut vault_data =
+        token_account_data(&loan_mint, &pda_key, OTHER_LENDER_LENT_AMOUNT + LENT_AMOUNT);
+    let mut token_program_lamports = 1u64;
+    let mut token_program_data = vec![];
+    let mut loan_info_lamports = 1u64;
+    let mut loan_info_data = loan_info_data(
+        &borrower_main_pubkey,
+        &loan_mint,
+        vec![
+            LoanInfoAccLendersData {
+                lender_main_acc_pubkey: other_lender_main_pubkey,
+                lent_amount: OTHER_LENDER_LENT_AMOUNT,
+            },
+            LoanInfoAccLendersData {
+                lender_main_acc_pubkey: lender_main_key,
+                lent_amount: LENT_AMOUNT,
+            },
+        ],
+    );
+    let mut lender_storage_lamports = 1u64;
+    let mut lender_storage_data = lender_account_data(&lender_main_key, &loan_mint, LENT_AMOUNT, 0);
+    let mut lender_position_lamports = 1u64;
+    let mut lender_position_data = lender_position_data(&lender_main_key, &loan_info_key);
+    let mut pda_lamports = 0u64;
+    let mut pda_data = vec![];
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&loan_mint);
+
+    let accounts = vec![
+        AccountInfo::new(
+            &lender_main_key,
+            true,
+            false,
+            &mut lender_main_lamports,
+            &mut lender_main_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &lender_ata_key,
+            false,
+            true,
+            &mut lender_ata_lamports,
+            &mut lender_ata_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &vault_key,
+            false,
+            true,
+            &mut vault_lamports,
+            &mut vault_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &token_program_key,
+            false,
+            false,
+            &mut token_program_lamports,
+            &mut token_program_data,
+            &system_program_key,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &loan_info_key,
+            false,
+            true,
+            &mut loan_info_lamports,
+            &mut loan_info_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &lender_storage_key,
+            false,
+            true,
+            &mut lender_storage_lamports,
+            &mut lender_storage_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &lender_position_key,
+            false,
+            true,
+            &mut lender_position_lamports,
+            &mut lender_position_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &pda_key,
+            false,
+            false,
+            &mut pda_lamports,
+            &mut pda_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            false,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    // slot of another lender can't be cancelled, even if caller expected own slot at that index
+    let other_slot_instruction_data = DassiInstruction::CancelLendingCommitment {
+        lender_index_input: 0,
+        amount_to_cancel_input: LENT_AMOUNT,
+    }
+    .pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &other_slot_instruction_data),
+        Err(DassiError::LenderAccountMismatched.into())
+    );
+
+    // a partial cancel still has to be in lending units
+    let partial_cancel_instruction_data = DassiInstruction::CancelLendingCommitment {
+        lender_index_input: 1,
+        amount_to_cancel_input: 5_000_000,
+    }
+    .pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &partial_cancel_instruction_data),
+        Err(DassiError::ExpectedAmountMismatch.into())
+    );
+
+    let instruction_data = DassiInstruction::CancelLendingCommitment {
+        lender_index_input: 1,
+        amount_to_cancel_input: LENT_AMOUNT,
+    }
+    .pack();
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+
+    let loan_info_header_data = state::unpack_to_loan_info_header(
+        &accounts[4].data.borrow()
+            [state::LOAN_INFO_HEADER_START_INDEX..state::LOAN_INFO_HEADER_END_INDEX],
+    )
+    .unwrap();
+    assert_eq!(loan_info_header_data.next_index_to_store_lender_data, 1);
+    assert_eq!(
+        loan_info_header_data.total_amount_lended,
+        OTHER_LENDER_LENT_AMOUNT
+    );
+
+    let lender_data = LenderAccount::unpack(&accounts[5].data.borrow()).unwrap();
+    assert_eq!(lender_data.total_unique_lending_amount, 0);
+
+    let lender_position_data = LenderPositionAccount::unpack(&accounts[6].data.borrow()).unwrap();
+    assert_eq!(lender_position_data.principal_lent, 0);
+    assert_eq!(
+        lender_position_data.position_status,
+        LenderPositionStatus::Cancelled as u8
+    );
+
+    let lender_ata_data = TokenAccount::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(lender_ata_data.amount, LENT_AMOUNT);
+    let vault_data = TokenAccount::unpack(&accounts[2].data.borrow()).unwrap();
+    assert_eq!(vault_data.amount, OTHER_LENDER_LENT_AMOUNT);
+}
@@ -0,0 +1,145 @@
+// helpers shared by tests that run the processor outside of the runtime, not every test uses all of them
+#![allow(dead_code)]
+
+use dassi_solana_program::state::{
+    AccTypes, LenderAccount, ProtocolConfig, ProtocolConfigParams, MAX_ACCEPTED_LOAN_MINTS,
+};
+use solana_program::account_info::AccountInfo;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{self, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+pub fn config_data(dassi_coin_mint: &Pubkey) -> Vec<u8> {
+    let mut accepted_loan_mints = [Pubkey::default(); MAX_ACCEPTED_LOAN_MINTS];
+    accepted_loan_mints[0] = *dassi_coin_mint;
+    let config = ProtocolConfig {
+        is_initialized: true,
+        acc_type: AccTypes::ConfigAcc as u8,
+        admin: Pubkey::new_unique(),
+        pending_admin: Pubkey::default(),
+        pauser: Pubkey::new_unique(),
+        risk_manager: Pubkey::new_unique(),
+        paused_operations: 0,
+        legacy_vault_migrated: true,
+        params: ProtocolConfigParams {
+            accepted_mint: *dassi_coin_mint,
+            min_lending_amount: 10,
+            airdrop_amount: 250,
+            max_airdrop_amount_per_user: 2_500,
+            late_payment_grace_period_days: 5,
+            initial_credit_score: 500,
+            late_fee_bps: 200,
+            guarantor_stake_coverage_bps: 2_000,
+            accepted_loan_mints,
+        },
+    };
+    let mut data = vec![0u8; ProtocolConfig::LEN];
+    ProtocolConfig::pack(config, &mut data).unwrap();
+    data
+}
+
+pub fn lender_account_data(
+    lender_main_pubkey: &Pubkey,
+    mint: &Pubkey,
+    total_unique_lending_amount: u64,
+    amount_to_withdraw_or_lend: u64,
+) -> Vec<u8> {
+    let mut data = vec![0u8; LenderAccount::LEN];
+    let mut lender_data = LenderAccount::unpack_unchecked(&data).unwrap();
+    lender_data.is_initialized = true;
+    lender_data.acc_type = AccTypes::LenderAcc as u8;
+    lender_data.lender_main_acc_pubkey = *lender_main_pubkey;
+    lender_data.mint = *mint;
+    lender_data.total_lending_amount = total_unique_lending_amount as u128;
+    lender_data.total_unique_lending_amount = total_unique_lending_amount;
+    lender_data.amount_to_withdraw_or_lend = amount_to_withdraw_or_lend;
+    LenderAccount::pack(lender_data, &mut data).unwrap();
+    data
+}
+
+pub fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let token_account = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    let mut data = vec![0u8; TokenAccount::LEN];
+    TokenAccount::pack(token_account, &mut data).unwrap();
+    data
+}
+
+pub fn mint_data(decimals: u8) -> Vec<u8> {
+    let mint = Mint {
+        mint_authority: COption::None,
+        supply: 0,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0u8; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+    data
+}
+
+// Clock sysvar returns unix_timestamp and cross program invocations of the token program are run by spl token processor,
+// pda signatures are taken as given
+struct TestSyscallStubs {
+    unix_timestamp: i64,
+}
+
+impl SyscallStubs for TestSyscallStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: self.unix_timestamp,
+            ..Clock::default()
+        };
+        unsafe {
+            *(var_addr as *mut Clock) = clock;
+        }
+        solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        assert_eq!(instruction.program_id, spl_token::id());
+        let instruction_account_infos: Vec<AccountInfo> = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                let account_info = account_infos
+                    .iter()
+                    .find(|account_info| *account_info.key == meta.pubkey)
+                    .unwrap();
+                // like the runtime, an account listed more than once is a signer if any of its metas is
+                let is_signer = instruction
+                    .accounts
+                    .iter()
+                    .any(|other_meta| other_meta.pubkey == meta.pubkey && other_meta.is_signer);
+                AccountInfo {
+                    is_signer,
+                    ..account_info.clone()
+                }
+            })
+            .collect();
+        spl_token::processor::Processor::process(
+            &instruction.program_id,
+            &instruction_account_infos,
+            &instruction.data,
+        )
+    }
+}
+
+pub fn set_syscall_stubs(unix_timestamp: i64) {
+    program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs { unix_timestamp }));
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use common::{config_data, mint_data};
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::{AccTypes, BorrowerAccount, GuarantorAccount};
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+fn borrower_data(borrower_main_pubkey: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; BorrowerAccount::LEN];
+    let mut borrower_data = BorrowerAccount::unpack_unchecked(&data).unwrap();
+    borrower_data.is_initialized = true;
+    borrower_data.acc_type = AccTypes::BorrowerAcc as u8;
+    borrower_data.borrower_main_acc_pubkey = *borrower_main_pubkey;
+    BorrowerAccount::pack(borrower_data, &mut data).unwrap();
+    data
+}
+
+fn guarantor_data(guarantor_main_pubkey: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; GuarantorAccount::LEN];
+    let mut guarantor_data = GuarantorAccount::unpack_unchecked(&data).unwrap();
+    guarantor_data.is_initialized = true;
+    guarantor_data.acc_type = AccTypes::GuarantorAcc as u8;
+    guarantor_data.guarantor_main_acc_pubkey = *guarantor_main_pubkey;
+    GuarantorAccount::pack(guarantor_data, &mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_reject_loan_below_min_lending_amount() {
+    let program_id = Pubkey::new_unique();
+    let dassi_coin_mint = Pubkey::new_unique();
+
+    // min lending amount of config is 10 tokens, 10_000_000 base units of a mint with 6 decimals
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&dassi_coin_mint);
+
+    let guarantor_key = Pubkey::new_unique();
+    let borrower_key = Pubkey::new_unique();
+    let loan_info_key = utils::get_loan_info_address(&borrower_key, 0, &program_id).0;
+    let borrower_storage_key = utils::get_borrower_storage_address(&borrower_key, &program_id).0;
+    let guarantor_storage_key = utils::get_guarantor_storage_address(&guarantor_key, &program_id).0;
+    let payer_key = Pubkey::new_unique();
+    let token_program_key = spl_token::id();
+    let system_program_key = system_program::id();
+
+    let mut guarantor_lamports = 0u64;
+    let mut guarantor_main_data = vec![];
+    let mut borrower_lamports = 0u64;
+    let mut borrower_main_data = vec![];
+    let mut loan_info_lamports = 0u64;
+    let mut loan_info_data = vec![];
+    let mut borrower_storage_lamports = 1u64;
+    let mut borrower_storage_data = borrower_data(&borrower_key);
+    let mut guarantor_storage_lamports = 1u64;
+    let mut guarantor_storage_data = guarantor_data(&guarantor_key);
+    let mut loan_mint_lamports = 1u64;
+    let mut loan_mint_data = mint_data(6);
+    let mut payer_lamports = 1_000_000_000u64;
+    let mut payer_data = vec![];
+    let mut system_program_lamports = 1u64;
+    let mut system_program_data = vec![];
+
+    let accounts = vec![
+        AccountInfo::new(
+            &guarantor_key,
+            true,
+            false,
+            &mut guarantor_lamports,
+            &mut guarantor_main_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &borrower_key,
+            false,
+            false,
+            &mut borrower_lamports,
+            &mut borrower_main_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &loan_info_key,
+            false,
+            true,
+            &mut loan_info_lamports,
+            &mut loan_info_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &borrower_storage_key,
+            false,
+            true,
+            &mut borrower_storage_lamports,
+            &mut borrower_storage_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &guarantor_storage_key,
+            false,
+            false,
+            &mut guarantor_storage_lamports,
+            &mut guarantor_storage_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &dassi_coin_mint,
+            false,
+            false,
+            &mut loan_mint_lamports,
+            &mut loan_mint_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &payer_key,
+            true,
+            true,
+            &mut payer_lamports,
+            &mut payer_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_key,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_key,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            false,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    for total_loan_amount_input in [0u64, 9_999_999].iter() {
+        let instruction_data = DassiInstruction::InitializeLoanInfoAccount {
+            num_days_left_for_first_repayment_input: 30,
+            num_emis_needed_to_repay_the_loan_input: 12,
+            num_days_for_fundraising_input: 7,
+            total_loan_amount_input: *total_loan_amount_input,
+            annual_interest_rate_bps_input: 1_200,
+            amortization_type_input: 0,
+            repayment_interval_type_input: 0,
+            custom_repayment_interval_days_input: 0,
+        }
+        .pack();
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &instruction_data),
+            Err(DassiError::InvalidLoanParameters.into())
+        );
+        // failed instruction doesn't revert account data here like the runtime does
+        accounts[3]
+            .data
+            .borrow_mut()
+            .copy_from_slice(&borrower_data(&borrower_key));
+    }
+}
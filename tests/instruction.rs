@@ -0,0 +1,269 @@
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::{DassiInstruction, INSTRUCTION_VERSION};
+use dassi_solana_program::state::ProtocolConfigParams;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+const NUMBER_OF_INSTRUCTION_TAGS: u8 = 35;
+const NUM_RANDOM_CASES: usize = 2_000;
+
+// xorshift64, enough to generate deterministic random instructions without extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // mostly uniform values, but edge values show up often too
+    fn any_u64(&mut self) -> u64 {
+        match self.next_u64() % 8 {
+            0 => 0,
+            1 => u64::MAX,
+            _ => self.next_u64(),
+        }
+    }
+
+    fn any_u32(&mut self) -> u32 {
+        self.any_u64() as u32
+    }
+
+    fn any_u16(&mut self) -> u16 {
+        self.any_u64() as u16
+    }
+
+    fn any_u8(&mut self) -> u8 {
+        self.any_u64() as u8
+    }
+
+    fn any_pubkey(&mut self) -> Pubkey {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes());
+        }
+        Pubkey::new_from_array(bytes)
+    }
+
+    fn any_config_params(&mut self) -> ProtocolConfigParams {
+        ProtocolConfigParams {
+            accepted_mint: self.any_pubkey(),
+            min_lending_amount: self.any_u64(),
+            airdrop_amount: self.any_u64(),
+            max_airdrop_amount_per_user: self.any_u64(),
+            late_payment_grace_period_days: self.any_u16(),
+            initial_credit_score: self.any_u64(),
+            late_fee_bps: self.any_u16(),
+            guarantor_stake_coverage_bps: self.any_u16(),
+            accepted_loan_mints: [
+                self.any_pubkey(),
+                self.any_pubkey(),
+                self.any_pubkey(),
+                self.any_pubkey(),
+            ],
+        }
+    }
+}
+
+fn random_instruction(rng: &mut Rng, tag: u8) -> DassiInstruction {
+    match tag {
+        0 => DassiInstruction::LendToBorrower {
+            amount_to_lend_input: rng.any_u64(),
+        },
+        1 => DassiInstruction::WithdrawLenderFreeWalletFunds {},
+        2 => DassiInstruction::WithdrawCollectedLoanFunds {},
+        3 => DassiInstruction::TransferDassiVaultAccountOwnership {},
+        4 => DassiInstruction::InitializeLendersStorageAccount {},
+        5 => DassiInstruction::InitializeBorrowerAccount {},
+        6 => DassiInstruction::InitializeGuarantorAccount {},
+        7 => DassiInstruction::PayEMIforLoan {
+            emi_amount_to_pay_input: rng.any_u64(),
+        },
+        8 => DassiInstruction::InitializeLoanInfoAccount {
+            num_days_left_for_first_repayment_input: rng.any_u16(),
+            num_emis_needed_to_repay_the_loan_input: rng.any_u16(),
+            num_days_for_fundraising_input: rng.any_u16(),
+            total_loan_amount_input: rng.any_u64(),
+            annual_interest_rate_bps_input: rng.any_u16(),
+            amortization_type_input: rng.any_u8(),
+            repayment_interval_type_input: rng.any_u8(),
+            custom_repayment_interval_days_input: rng.any_u16(),
+        },
+        9 => DassiInstruction::AirdropUsersWithDassiTestCoins {},
+        10 => DassiInstruction::TransferAirdropVaultAccountOwnership {},
+        11 => DassiInstruction::ReturnFundsToLenders {
+            num_accounts_input: rng.any_u16(),
+        },
+        12 => DassiInstruction::CloseLoanInfoAccount {},
+        13 => DassiInstruction::MarkLoanDefaulted {},
+        14 => DassiInstruction::PayDefaultedLoanAsGuarantor {
+            amount_to_pay_input: rng.any_u64(),
+        },
+        15 => DassiInstruction::InitializeGuarantorStakeVault {},
+        16 => DassiInstruction::StakeGuarantorCollateral {
+            amount_to_stake_input: rng.any_u64(),
+        },
+        17 => DassiInstruction::UnstakeGuarantorCollateral {
+            amount_to_unstake_input: rng.any_u64(),
+        },
+        18 => DassiInstruction::InitializeConfig {
+            params_input: rng.any_config_params(),
+        },
+        19 => DassiInstruction::UpdateConfig {
+            params_input: rng.any_config_params(),
+        },
+        20 => DassiInstruction::ProposeAdmin {
+            new_admin_input: rng.any_pubkey(),
+        },
+        21 => DassiInstruction::AcceptAdmin {},
+        22 => DassiInstruction::SetRoles {
+            pauser_input: rng.any_pubkey(),
+            risk_manager_input: rng.any_pubkey(),
+        },
+        23 => DassiInstruction::PauseOperations {
+            operations_input: rng.any_u8(),
+        },
+        24 => DassiInstruction::UnpauseOperations {
+            operations_input: rng.any_u8(),
+        },
+        25 => DassiInstruction::InitializeLenderAccount {},
+        26 => DassiInstruction::MigrateLenderAccount {
+            lender_id_input: rng.any_u32(),
+        },
+        27 => DassiInstruction::CreditRepaymentsToLenders {
+            num_accounts_input: rng.any_u16(),
+        },
+        28 => DassiInstruction::CancelLendingCommitment {
+            lender_index_input: rng.any_u8(),
+            amount_to_cancel_input: rng.any_u64(),
+        },
+        29 => DassiInstruction::RelendFromFreeBalance {
+            amount_to_lend_input: rng.any_u64(),
+        },
+        30 => DassiInstruction::SetLendingStrategy {
+            max_amount_per_loan_input: rng.any_u64(),
+            min_credit_score_input: rng.any_u64(),
+            min_approval_score_input: rng.any_u64(),
+        },
+        31 => DassiInstruction::AllocateFreeBalanceToLoans {
+            num_loans_input: rng.any_u8(),
+        },
+        32 => DassiInstruction::InitializeMintVault {},
+        33 => DassiInstruction::AddAcceptedLoanMint {},
+        34 => DassiInstruction::MigrateLegacyVault {},
+        _ => unreachable!(),
+    }
+}
+
+fn random_instructions() -> Vec<DassiInstruction> {
+    let mut rng = Rng(0x5eed_da55_1f1a_a7ce);
+    (0..NUM_RANDOM_CASES)
+        .map(|i| random_instruction(&mut rng, (i % NUMBER_OF_INSTRUCTION_TAGS as usize) as u8))
+        .collect()
+}
+
+#[test]
+fn test_pack_unpack_round_trip() {
+    for instruction in random_instructions() {
+        let packed = instruction.pack();
+        assert_eq!(packed[0], INSTRUCTION_VERSION);
+        assert_eq!(DassiInstruction::unpack(&packed).unwrap(), instruction);
+    }
+}
+
+#[test]
+fn test_every_tag_is_covered() {
+    for tag in 0..NUMBER_OF_INSTRUCTION_TAGS {
+        let packed = random_instruction(&mut Rng(1), tag).pack();
+        assert_eq!(packed[1], tag);
+    }
+    assert_eq!(
+        DassiInstruction::unpack(&[INSTRUCTION_VERSION, NUMBER_OF_INSTRUCTION_TAGS]),
+        Err(DassiError::InvalidInstruction.into())
+    );
+}
+
+#[test]
+fn test_wire_layout() {
+    let packed = DassiInstruction::LendToBorrower {
+        amount_to_lend_input: 10_000_000_000,
+    }
+    .pack();
+    let mut expected = vec![INSTRUCTION_VERSION, 0];
+    expected.extend_from_slice(&10_000_000_000u64.to_le_bytes());
+    assert_eq!(packed, expected);
+}
+
+#[test]
+fn test_unpack_empty_data() {
+    assert_eq!(
+        DassiInstruction::unpack(&[]),
+        Err(DassiError::InvalidInstruction.into())
+    );
+    assert_eq!(
+        DassiInstruction::unpack(&[INSTRUCTION_VERSION]),
+        Err(DassiError::InvalidInstruction.into())
+    );
+}
+
+#[test]
+fn test_unpack_unsupported_version() {
+    for instruction in random_instructions().iter().take(NUMBER_OF_INSTRUCTION_TAGS as usize) {
+        let mut packed = instruction.pack();
+        packed[0] = INSTRUCTION_VERSION.wrapping_add(1);
+        assert_eq!(
+            DassiInstruction::unpack(&packed),
+            Err(DassiError::UnsupportedInstructionVersion.into())
+        );
+    }
+}
+
+#[test]
+fn test_unpack_truncated_data() {
+    for instruction in random_instructions().iter().take(NUMBER_OF_INSTRUCTION_TAGS as usize) {
+        let packed = instruction.pack();
+        for len in 1..packed.len() {
+            assert_eq!(
+                DassiInstruction::unpack(&packed[..len]),
+                Err(DassiError::InvalidInstruction.into()),
+                "{:?} truncated to {} bytes",
+                instruction,
+                len
+            );
+        }
+    }
+}
+
+#[test]
+fn test_unpack_trailing_bytes() {
+    let mut rng = Rng(42);
+    for instruction in random_instructions() {
+        let mut packed = instruction.pack();
+        let num_trailing_bytes = 1 + rng.next_u64() % 8;
+        for _ in 0..num_trailing_bytes {
+            packed.push(rng.any_u8());
+        }
+        let result: Result<DassiInstruction, ProgramError> = DassiInstruction::unpack(&packed);
+        assert_eq!(
+            result,
+            Err(DassiError::InstructionDataHasTrailingBytes.into())
+        );
+    }
+}
+
+#[test]
+fn test_unpack_random_bytes_never_panics() {
+    let mut rng = Rng(7);
+    for _ in 0..NUM_RANDOM_CASES {
+        let len = (rng.next_u64() % 32) as usize;
+        let mut data: Vec<u8> = (0..len).map(|_| rng.any_u8()).collect();
+        if let Some(version) = data.first_mut() {
+            *version = INSTRUCTION_VERSION;
+        }
+        if let Ok(instruction) = DassiInstruction::unpack(&data) {
+            assert_eq!(instruction.pack(), data);
+        }
+    }
+}
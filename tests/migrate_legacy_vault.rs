@@ -0,0 +1,134 @@
+mod common;
+
+use common::{config_data, set_syscall_stubs, token_account_data};
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::ProtocolConfig;
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use spl_token::state::Account as TokenAccount;
+
+const LEGACY_VAULT_BALANCE: u64 = 50_000_000_000;
+const MINT_VAULT_BALANCE: u64 = 5_000_000_000;
+
+#[test]
+fn test_migrate_legacy_vault_once() {
+    set_syscall_stubs(0);
+
+    let program_id = Pubkey::new_unique();
+    let dassi_coin_mint = Pubkey::new_unique();
+
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&dassi_coin_mint);
+    let mut config = ProtocolConfig::unpack(&config_data).unwrap();
+    config.legacy_vault_migrated = false;
+    let admin_key = config.admin;
+    ProtocolConfig::pack(config, &mut config_data).unwrap();
+
+    let legacy_vault_key = Pubkey::new_unique();
+    let mint_vault_key = utils::get_mint_vault_address(&dassi_coin_mint, &program_id).0;
+    let token_program_key = spl_token::id();
+    let pda_key = Pubkey::find_program_address(&[b"DassiFinance"], &program_id).0;
+    let system_program_key = system_program::id();
+
+    let mut admin_lamports = 1_000_000_000u64;
+    let mut admin_data = vec![];
+    let mut legacy_vault_lamports = 1u64;
+    let mut legacy_vault_data =
+        token_account_data(&dassi_coin_mint, &pda_key, LEGACY_VAULT_BALANCE);
+    let mut mint_vault_lamports = 1u64;
+    let mut mint_vault_data = token_account_data(&dassi_coin_mint, &pda_key, MINT_VAULT_BALANCE);
+    let mut token_program_lamports = 1u64;
+    let mut token_program_data = vec![];
+    let mut pda_lamports = 0u64;
+    let mut pda_data = vec![];
+
+    let accounts = vec![
+        AccountInfo::new(
+            &admin_key,
+            true,
+            false,
+            &mut admin_lamports,
+            &mut admin_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &legacy_vault_key,
+            false,
+            true,
+            &mut legacy_vault_lamports,
+            &mut legacy_vault_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &mint_vault_key,
+            false,
+            true,
+            &mut mint_vault_lamports,
+            &mut mint_vault_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &token_program_key,
+            false,
+            false,
+            &mut token_program_lamports,
+            &mut token_program_data,
+            &system_program_key,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &pda_key,
+            false,
+            false,
+            &mut pda_lamports,
+            &mut pda_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    let instruction_data = DassiInstruction::MigrateLegacyVault {}.pack();
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+
+    let legacy_vault_data = TokenAccount::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(legacy_vault_data.amount, 0);
+    let mint_vault_data = TokenAccount::unpack(&accounts[2].data.borrow()).unwrap();
+    assert_eq!(
+        mint_vault_data.amount,
+        LEGACY_VAULT_BALANCE + MINT_VAULT_BALANCE
+    );
+    assert!(
+        ProtocolConfig::unpack(&accounts[5].data.borrow())
+            .unwrap()
+            .legacy_vault_migrated
+    );
+
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::LegacyVaultAlreadyMigrated.into())
+    );
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use common::{config_data, lender_account_data};
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::{AccTypes, LenderAccount, ProtocolConfig};
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+// layout of the lenders storage account deployed before lender accounts: 2 header bytes followed by 65 byte slots of
+// is_account_active, lender main account, total_lending_amount (u128), total_unique_lending_amount, amount_to_withdraw_or_lend
+const DEPLOYED_LENDERS_STORAGE_SIZE: usize = 3_250_002;
+const DEPLOYED_LENDER_SLOT_SIZE: usize = 65;
+
+fn deployed_lenders_storage(
+    lender_id: usize,
+    lender_main_pubkey: &Pubkey,
+    total_lending_amount: u128,
+    total_unique_lending_amount: u64,
+    amount_to_withdraw_or_lend: u64,
+) -> Vec<u8> {
+    let mut data = vec![0u8; DEPLOYED_LENDERS_STORAGE_SIZE];
+    data[0] = AccTypes::LendersAcc as u8;
+    data[1] = 1;
+    let mut slot = vec![1u8];
+    slot.extend_from_slice(lender_main_pubkey.as_ref());
+    slot.extend_from_slice(&total_lending_amount.to_le_bytes());
+    slot.extend_from_slice(&total_unique_lending_amount.to_le_bytes());
+    slot.extend_from_slice(&amount_to_withdraw_or_lend.to_le_bytes());
+    assert_eq!(slot.len(), DEPLOYED_LENDER_SLOT_SIZE);
+    let slot_start = 2 + lender_id * DEPLOYED_LENDER_SLOT_SIZE;
+    data[slot_start..slot_start + DEPLOYED_LENDER_SLOT_SIZE].copy_from_slice(&slot);
+    data
+}
+
+#[test]
+fn test_migrate_deployed_lender_slot() {
+    let program_id = Pubkey::new_unique();
+    let dassi_coin_mint = Pubkey::new_unique();
+    let lender_main_pubkey = Pubkey::new_unique();
+    let lender_id = 1_234usize;
+
+    let payer_key = Pubkey::new_unique();
+    let lenders_storage_key = Pubkey::new_unique();
+    let lender_storage_key =
+        utils::get_lender_storage_address(&lender_main_pubkey, &dassi_coin_mint, &program_id).0;
+    let system_program_key = system_program::id();
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+
+    let mut payer_lamports = 1_000_000_000u64;
+    let mut payer_data = vec![];
+    let mut lenders_storage_lamports = 1u64;
+    let mut lenders_storage_data = deployed_lenders_storage(
+        lender_id,
+        &lender_main_pubkey,
+        700_000_000_000,
+        500_000_000_000,
+        40_000_000_000,
+    );
+    // lender already created his lender account, so migration adds legacy balances to it
+    let mut lender_storage_lamports = 1u64;
+    let mut lender_storage_data = lender_account_data(
+        &lender_main_pubkey,
+        &dassi_coin_mint,
+        10_000_000_000,
+        10_000_000_000,
+    );
+    let mut system_program_lamports = 1u64;
+    let mut system_program_data = vec![];
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&dassi_coin_mint);
+
+    let accounts = vec![
+        AccountInfo::new(
+            &payer_key,
+            true,
+            true,
+            &mut payer_lamports,
+            &mut payer_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &lenders_storage_key,
+            false,
+            true,
+            &mut lenders_storage_lamports,
+            &mut lenders_storage_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &lender_storage_key,
+            false,
+            true,
+            &mut lender_storage_lamports,
+            &mut lender_storage_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program_key,
+            false,
+            false,
+            &mut system_program_lamports,
+            &mut system_program_data,
+            &system_program_key,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            false,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    let instruction_data = DassiInstruction::MigrateLenderAccount {
+        lender_id_input: lender_id as u32,
+    }
+    .pack();
+
+    // migrated balances are not backed by vault of DassiCoin mint until legacy vault is migrated
+    let mut config = ProtocolConfig::unpack(&accounts[4].data.borrow()).unwrap();
+    config.legacy_vault_migrated = false;
+    ProtocolConfig::pack(config, &mut accounts[4].data.borrow_mut()).unwrap();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::LegacyVaultNotMigrated.into())
+    );
+    let mut config = ProtocolConfig::unpack(&accounts[4].data.borrow()).unwrap();
+    config.legacy_vault_migrated = true;
+    ProtocolConfig::pack(config, &mut accounts[4].data.borrow_mut()).unwrap();
+
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+
+    let lender_data = LenderAccount::unpack(&accounts[2].data.borrow()).unwrap();
+    assert_eq!(lender_data.lender_main_acc_pubkey, lender_main_pubkey);
+    assert_eq!(lender_data.mint, dassi_coin_mint);
+    assert_eq!(lender_data.total_lending_amount, 710_000_000_000);
+    assert_eq!(lender_data.total_unique_lending_amount, 510_000_000_000);
+    assert_eq!(lender_data.amount_to_withdraw_or_lend, 50_000_000_000);
+    assert_eq!(lender_data.total_interest_earned, 0);
+
+    let slot_start = 2 + lender_id * DEPLOYED_LENDER_SLOT_SIZE;
+    assert!(
+        accounts[1].data.borrow()[slot_start..slot_start + DEPLOYED_LENDER_SLOT_SIZE]
+            .iter()
+            .all(|byte| *byte == 0)
+    );
+
+    // slot is cleared, so it can't be migrated again
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::InvalidLenderIdInput.into())
+    );
+}
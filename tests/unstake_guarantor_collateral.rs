@@ -0,0 +1,182 @@
+mod common;
+
+use common::{config_data, mint_data, set_syscall_stubs, token_account_data};
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::{AccTypes, GuarantorStakeAccount, ProtocolConfig};
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use spl_token::state::Account as TokenAccount;
+
+const STAKED_AMOUNT: u64 = 100_000_000;
+const LOCKED_STAKE_AMOUNT: u64 = 60_000_000;
+
+fn guarantor_stake_data(guarantor_main_pubkey: &Pubkey, mint: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; GuarantorStakeAccount::LEN];
+    let mut guarantor_stake_data = GuarantorStakeAccount::unpack_unchecked(&data).unwrap();
+    guarantor_stake_data.is_initialized = true;
+    guarantor_stake_data.acc_type = AccTypes::GuarantorStakeAcc as u8;
+    guarantor_stake_data.guarantor_main_acc_pubkey = *guarantor_main_pubkey;
+    guarantor_stake_data.mint = *mint;
+    guarantor_stake_data.staked_amount = STAKED_AMOUNT;
+    guarantor_stake_data.locked_stake_amount = LOCKED_STAKE_AMOUNT;
+    GuarantorStakeAccount::pack(guarantor_stake_data, &mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_unstake_only_free_stake_of_loan_mint() {
+    set_syscall_stubs(0);
+
+    let program_id = Pubkey::new_unique();
+    let dassi_coin_mint = Pubkey::new_unique();
+    let loan_mint = Pubkey::new_unique();
+
+    // stake is in a loan mint other than DassiCoin
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&dassi_coin_mint);
+    let mut config = ProtocolConfig::unpack(&config_data).unwrap();
+    config.params.accepted_loan_mints[1] = loan_mint;
+    ProtocolConfig::pack(config, &mut config_data).unwrap();
+
+    let guarantor_key = Pubkey::new_unique();
+    let guarantor_ata_key = Pubkey::new_unique();
+    let guarantor_stake_key =
+        utils::get_guarantor_stake_address(&guarantor_key, &loan_mint, &program_id).0;
+    let guarantor_stake_vault_key =
+        utils::get_guarantor_stake_vault_address(&guarantor_stake_key, &program_id).0;
+    let token_program_key = spl_token::id();
+    let pda_key = Pubkey::find_program_address(&[b"DassiFinance"], &program_id).0;
+    let system_program_key = system_program::id();
+
+    let mut guarantor_lamports = 1_000_000_000u64;
+    let mut guarantor_data = vec![];
+    let mut guarantor_ata_lamports = 1u64;
+    let mut guarantor_ata_data = token_account_data(&loan_mint, &guarantor_key, 0);
+    let mut stake_vault_lamports = 1u64;
+    let mut stake_vault_data = token_account_data(&loan_mint, &pda_key, STAKED_AMOUNT);
+    let mut token_program_lamports = 1u64;
+    let mut token_program_data = vec![];
+    let mut guarantor_stake_lamports = 1u64;
+    let mut guarantor_stake_data = guarantor_stake_data(&guarantor_key, &loan_mint);
+    let mut pda_lamports = 0u64;
+    let mut pda_data = vec![];
+    let mut loan_mint_lamports = 1u64;
+    let mut loan_mint_data = mint_data(6);
+
+    let accounts = vec![
+        AccountInfo::new(
+            &guarantor_key,
+            true,
+            false,
+            &mut guarantor_lamports,
+            &mut guarantor_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &guarantor_ata_key,
+            false,
+            true,
+            &mut guarantor_ata_lamports,
+            &mut guarantor_ata_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &guarantor_stake_vault_key,
+            false,
+            true,
+            &mut stake_vault_lamports,
+            &mut stake_vault_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &token_program_key,
+            false,
+            false,
+            &mut token_program_lamports,
+            &mut token_program_data,
+            &system_program_key,
+            true,
+            0,
+        ),
+        AccountInfo::new(
+            &guarantor_stake_key,
+            false,
+            true,
+            &mut guarantor_stake_lamports,
+            &mut guarantor_stake_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &pda_key,
+            false,
+            false,
+            &mut pda_lamports,
+            &mut pda_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &loan_mint,
+            false,
+            false,
+            &mut loan_mint_lamports,
+            &mut loan_mint_data,
+            &token_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            false,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    // stake locked for active loans can't be taken back
+    let free_stake_amount = STAKED_AMOUNT - LOCKED_STAKE_AMOUNT;
+    let instruction_data = DassiInstruction::UnstakeGuarantorCollateral {
+        amount_to_unstake_input: free_stake_amount + 1,
+    }
+    .pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::InsufficientGuarantorStake.into())
+    );
+
+    let instruction_data = DassiInstruction::UnstakeGuarantorCollateral {
+        amount_to_unstake_input: free_stake_amount,
+    }
+    .pack();
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+
+    let guarantor_stake_data = GuarantorStakeAccount::unpack(&accounts[4].data.borrow()).unwrap();
+    assert_eq!(guarantor_stake_data.staked_amount, LOCKED_STAKE_AMOUNT);
+    assert_eq!(
+        guarantor_stake_data.locked_stake_amount,
+        LOCKED_STAKE_AMOUNT
+    );
+    let guarantor_ata_data = TokenAccount::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(guarantor_ata_data.amount, free_stake_amount);
+    let stake_vault_data = TokenAccount::unpack(&accounts[2].data.borrow()).unwrap();
+    assert_eq!(stake_vault_data.amount, LOCKED_STAKE_AMOUNT);
+}
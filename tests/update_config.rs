@@ -0,0 +1,76 @@
+mod common;
+
+use common::config_data;
+use dassi_solana_program::error::DassiError;
+use dassi_solana_program::instruction::DassiInstruction;
+use dassi_solana_program::processor::Processor;
+use dassi_solana_program::state::ProtocolConfig;
+use dassi_solana_program::utils;
+use solana_program::account_info::AccountInfo;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+#[test]
+fn test_risk_manager_updates_only_risk_parameters() {
+    let program_id = Pubkey::new_unique();
+    let dassi_coin_mint = Pubkey::new_unique();
+
+    let config_key = utils::get_protocol_config_address(&program_id).0;
+    let mut config_lamports = 1u64;
+    let mut config_data = config_data(&dassi_coin_mint);
+    let config = ProtocolConfig::unpack(&config_data).unwrap();
+
+    let risk_manager_key = config.risk_manager;
+    let system_program_key = system_program::id();
+    let mut risk_manager_lamports = 1_000_000_000u64;
+    let mut risk_manager_data = vec![];
+
+    let accounts = vec![
+        AccountInfo::new(
+            &risk_manager_key,
+            true,
+            false,
+            &mut risk_manager_lamports,
+            &mut risk_manager_data,
+            &system_program_key,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            &mut config_data,
+            &program_id,
+            false,
+            0,
+        ),
+    ];
+
+    let mut params_input = config.params;
+    params_input.accepted_mint = Pubkey::new_unique();
+    let instruction_data = DassiInstruction::UpdateConfig { params_input }.pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::ConfigMintsCanNotBeUpdated.into())
+    );
+
+    let mut params_input = config.params;
+    params_input.accepted_loan_mints[1] = Pubkey::new_unique();
+    let instruction_data = DassiInstruction::UpdateConfig { params_input }.pack();
+    assert_eq!(
+        Processor::process(&program_id, &accounts, &instruction_data),
+        Err(DassiError::ConfigMintsCanNotBeUpdated.into())
+    );
+
+    let mut params_input = config.params;
+    params_input.late_fee_bps = 300;
+    let instruction_data = DassiInstruction::UpdateConfig { params_input }.pack();
+    Processor::process(&program_id, &accounts, &instruction_data).unwrap();
+
+    let updated_config = ProtocolConfig::unpack(&accounts[1].data.borrow()).unwrap();
+    assert_eq!(updated_config.params.late_fee_bps, 300);
+    assert_eq!(updated_config.params.accepted_mint, dassi_coin_mint);
+}